@@ -1,4 +1,4 @@
-use gv_video::{get_bgra_vec_from_frame, get_rgb_vec_from_frame, get_rgba_from_frame, get_rgba_vec_from_frame, GVFormat, GVVideo, RGBAColor};
+use gv_video::{GVError, get_rgb_vec_from_frame, get_rgba_from_frame, get_rgba_vec_from_frame, GVFormat, GVVideo, RGBAColor};
 use std::{fs::File, io::BufReader};
 
 fn main() {
@@ -8,7 +8,7 @@ fn main() {
         let (w, h) = (10, 10);
 
         // load video
-        let mut video = GVVideo::load(&mut reader);
+        let mut video = GVVideo::load(&mut reader).unwrap();
 
         // or, simply use load_from_file
         // let mut video = GVVideo::load_from_file("test_asset/test-10px.gv").unwrap();
@@ -27,7 +27,7 @@ fn main() {
         assert_eq!(frame.len(), w * h);
         assert_eq!(frame[0], 0xFFFF0000); // x,y=0,0: red (0xAARRGGBB)
         assert_eq!(frame[6], 0xFF0000FF); // x,y=6,0: blue (0xAARRGGBB)
-        assert_eq!(frame[0 + w*6], 0xFF00FF00); // x,y=0,6: green (0xAARRGGBB)
+        assert_eq!(frame[w*6], 0xFF00FF00); // x,y=0,6: green (0xAARRGGBB)
         assert_eq!(frame[6 + w*6], 0xFFE7FF00); // x,y=6,6: yellow (0xAARRGGBB)
 
         // 4.99 sec
@@ -39,7 +39,7 @@ fn main() {
         let frame = video.read_frame_at(std::time::Duration::from_secs_f32(5.01));
 
         assert!(frame.is_err());
-        assert_eq!(frame.err(), Some("End of video"));
+        assert!(matches!(frame, Err(GVError::FrameOutOfRange { id: 5, count: 5 })));
 
         // read frame by index
         let frame = video.read_frame(0).unwrap();
@@ -52,7 +52,7 @@ fn main() {
 
         // convert frame to Vec<u8> BGRA ( [B,G,R,A,B,G,R,A,...] )
         // this is fastest way to get frame data as Vec<u8>
-        // let frame_u8 = get_bgra_vec_from_frame(frame);
+        // let frame_u8 = gv_video::get_bgra_vec_from_frame(frame);
 
         // convert frame to Vec<u8> RGBA ( [R,G,B,A,R,G,B,A,...] )
         // this is slower than direct BGRA conversion
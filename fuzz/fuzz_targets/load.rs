@@ -0,0 +1,12 @@
+#![no_main]
+
+use std::io::Cursor;
+
+use gv_video::GVVideo;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(mut video) = GVVideo::load(Cursor::new(data)) {
+        let _ = video.validate();
+    }
+});
@@ -0,0 +1,25 @@
+#![no_main]
+
+use std::io::Cursor;
+
+use gv_video::{GVLoadOptions, GVVideo};
+use libfuzzer_sys::fuzz_target;
+
+// small limits, so a crafted header cannot make a single run allocate much
+const OPTIONS: GVLoadOptions = GVLoadOptions {
+    max_width: 1024,
+    max_height: 1024,
+    max_frame_count: 1024,
+    max_frame_size: 1 << 20,
+};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(mut video) = GVVideo::load_with_options(Cursor::new(data), OPTIONS) else {
+        return;
+    };
+    for frame_id in 0..video.get_frame_count().min(4) {
+        let _ = video.read_frame(frame_id);
+        let _ = video.read_frame_compressed(frame_id);
+        let _ = video.read_frame_shared(frame_id);
+    }
+});
@@ -0,0 +1,416 @@
+// BC1 (DXT1) encoder
+//
+// range fit and cluster fit follow libsquish by Simon Brown (https://sourceforge.net/projects/libsquish/),
+// palettes are evaluated exactly as texture2ddecoder::decode_bc1_block reconstructs them.
+//
+// 3 color mode never uses index 3, because GPUs decode it as transparent black
+
+use std::sync::OnceLock;
+
+use crate::encoder::{encode_blocks, BCQuality};
+use crate::GVError;
+
+pub(crate) type Vec3 = [f32; 3];
+
+#[inline]
+fn add(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+#[inline]
+fn sub(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+#[inline]
+fn scale(a: Vec3, s: f32) -> Vec3 {
+    [a[0] * s, a[1] * s, a[2] * s]
+}
+
+#[inline]
+fn dot(a: Vec3, b: Vec3) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+/// BGRA u32 -> [r, g, b]
+#[inline]
+pub(crate) fn unpack_rgb(color: u32) -> Vec3 {
+    [((color >> 16) & 0xFF) as f32, ((color >> 8) & 0xFF) as f32, (color & 0xFF) as f32]
+}
+
+/// nearest RGB565 of color (0..255 per channel)
+#[inline]
+fn to_565(c: Vec3) -> u16 {
+    let r = (c[0].clamp(0.0, 255.0) * 31.0 / 255.0 + 0.5) as u16;
+    let g = (c[1].clamp(0.0, 255.0) * 63.0 / 255.0 + 0.5) as u16;
+    let b = (c[2].clamp(0.0, 255.0) * 31.0 / 255.0 + 0.5) as u16;
+    (r << 11) | (g << 5) | b
+}
+
+/// same expansion as texture2ddecoder's rgb565_le
+#[inline]
+fn from_565(q: u16) -> [i32; 3] {
+    let r = (q >> 11) & 0x1F;
+    let g = (q >> 5) & 0x3F;
+    let b = q & 0x1F;
+    [((r << 3) | (r >> 2)) as i32, ((g << 2) | (g >> 4)) as i32, ((b << 3) | (b >> 2)) as i32]
+}
+
+#[inline]
+fn snap_565(c: Vec3) -> Vec3 {
+    let [r, g, b] = from_565(to_565(c));
+    [r as f32, g as f32, b as f32]
+}
+
+/// principal axis of colors by power iteration on the covariance matrix
+fn principal_axis(colors: &[Vec3; 16]) -> Vec3 {
+    let mean = scale(colors.iter().fold([0.0; 3], |acc, &c| add(acc, c)), 1.0 / 16.0);
+
+    let mut cov = [[0.0f32; 3]; 3];
+    for &c in colors {
+        let d = sub(c, mean);
+        cov[0] = add(cov[0], scale(d, d[0]));
+        cov[1] = add(cov[1], scale(d, d[1]));
+        cov[2] = add(cov[2], scale(d, d[2]));
+    }
+
+    // start from the covariance row of largest variance, (1, 1, 1) can be orthogonal to the axis
+    let mut axis = [1.0, 1.0, 1.0];
+    let row = (0..3).fold(0, |best, i| if cov[i][i] > cov[best][best] { i } else { best });
+    if cov[row][row] > 0.0 {
+        axis = cov[row];
+    }
+    for _ in 0..8 {
+        let next = [dot(cov[0], axis), dot(cov[1], axis), dot(cov[2], axis)];
+        let len = next[0].abs().max(next[1].abs()).max(next[2].abs());
+        if len <= f32::EPSILON {
+            break;
+        }
+        axis = scale(next, 1.0 / len);
+    }
+    axis
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ColorBlock {
+    color0: u16,
+    color1: u16,
+    indices: u32,
+    error: u32,
+}
+
+impl ColorBlock {
+    fn to_bytes(self) -> [u8; 8] {
+        let mut data = [0; 8];
+        data[0..2].copy_from_slice(&self.color0.to_le_bytes());
+        data[2..4].copy_from_slice(&self.color1.to_le_bytes());
+        data[4..8].copy_from_slice(&self.indices.to_le_bytes());
+        data
+    }
+}
+
+/// quantize endpoints, order them for 4 or 3 color mode, then pick nearest palette entry for each pixel
+fn fit_endpoints(pixels: &[[i32; 3]; 16], a: Vec3, b: Vec3, four_color: bool) -> ColorBlock {
+    let (mut color0, mut color1) = (to_565(a), to_565(b));
+    if (four_color && color0 < color1) || (!four_color && color0 > color1) {
+        std::mem::swap(&mut color0, &mut color1);
+    }
+
+    let c0 = from_565(color0);
+    let c1 = from_565(color1);
+    let palette: [[i32; 3]; 4];
+    let palette_len;
+    if color0 == color1 {
+        // every index decodes to c0 (index 3 would be black or transparent)
+        palette = [c0; 4];
+        palette_len = 1;
+    } else if four_color {
+        palette = [
+            c0,
+            c1,
+            [(c0[0] * 2 + c1[0]) / 3, (c0[1] * 2 + c1[1]) / 3, (c0[2] * 2 + c1[2]) / 3],
+            [(c0[0] + c1[0] * 2) / 3, (c0[1] + c1[1] * 2) / 3, (c0[2] + c1[2] * 2) / 3],
+        ];
+        palette_len = 4;
+    } else {
+        palette = [c0, c1, [(c0[0] + c1[0]) / 2, (c0[1] + c1[1]) / 2, (c0[2] + c1[2]) / 2], [0; 3]];
+        palette_len = 3;
+    }
+
+    let mut indices = 0;
+    let mut error = 0;
+    for (i, p) in pixels.iter().enumerate() {
+        let mut best_index = 0;
+        let mut best_error = u32::MAX;
+        for (index, c) in palette[..palette_len].iter().enumerate() {
+            let dr = p[0] - c[0];
+            let dg = p[1] - c[1];
+            let db = p[2] - c[2];
+            let e = (dr * dr + dg * dg + db * db) as u32;
+            if e < best_error {
+                best_index = index as u32;
+                best_error = e;
+            }
+        }
+        indices |= best_index << (i * 2);
+        error += best_error;
+    }
+
+    ColorBlock { color0, color1, indices, error }
+}
+
+/// (color0, color1) 5 or 6 bit endpoint pair for each 8 bit value, such that (2 * c0 + c1) / 3 is nearest to it
+fn single_color_table(bits: u32) -> [(u8, u8); 256] {
+    let expand = |e: u32| if bits == 5 { (e << 3) | (e >> 2) } else { (e << 2) | (e >> 4) };
+    let mut table = [(0, 0); 256];
+    for (value, entry) in table.iter_mut().enumerate() {
+        let mut best_error = i32::MAX;
+        for e0 in 0..(1 << bits) {
+            for e1 in 0..(1 << bits) {
+                let c = ((expand(e0) * 2 + expand(e1)) / 3) as i32;
+                let error = (c - value as i32).abs() * 256 + (e0 as i32 - e1 as i32).abs();
+                if error < best_error {
+                    best_error = error;
+                    *entry = (e0 as u8, e1 as u8);
+                }
+            }
+        }
+    }
+    table
+}
+
+/// endpoints reproducing a solid color as closely as possible through the interpolated palette entry
+fn single_color_fit(color: Vec3) -> (Vec3, Vec3) {
+    static TABLE5: OnceLock<[(u8, u8); 256]> = OnceLock::new();
+    static TABLE6: OnceLock<[(u8, u8); 256]> = OnceLock::new();
+    let table5 = TABLE5.get_or_init(|| single_color_table(5));
+    let table6 = TABLE6.get_or_init(|| single_color_table(6));
+    let (r0, r1) = table5[color[0] as usize];
+    let (g0, g1) = table6[color[1] as usize];
+    let (b0, b1) = table5[color[2] as usize];
+    let pack = |r: u8, g: u8, b: u8| {
+        let [r, g, b] = from_565(((r as u16) << 11) | ((g as u16) << 5) | b as u16);
+        [r as f32, g as f32, b as f32]
+    };
+    (pack(r0, g0, b0), pack(r1, g1, b1))
+}
+
+/// endpoints at the extent of colors projected on the principal axis
+fn range_fit(colors: &[Vec3; 16], axis: Vec3) -> (Vec3, Vec3) {
+    let mut min = (f32::MAX, colors[0]);
+    let mut max = (f32::MIN, colors[0]);
+    for &c in colors {
+        let d = dot(c, axis);
+        if d < min.0 {
+            min = (d, c);
+        }
+        if d > max.0 {
+            max = (d, c);
+        }
+    }
+    (max.1, min.1)
+}
+
+/// least squares endpoints for clusters weighted (alpha, beta), with endpoints snapped to the 565 grid
+fn solve_clusters(alpha2: f32, beta2: f32, alphabeta: f32, alphax: Vec3, betax: Vec3) -> Option<(Vec3, Vec3, f32)> {
+    let det = alpha2 * beta2 - alphabeta * alphabeta;
+    if det.abs() < 1e-6 {
+        return None;
+    }
+    let a = snap_565(scale(sub(scale(alphax, beta2), scale(betax, alphabeta)), 1.0 / det));
+    let b = snap_565(scale(sub(scale(betax, alpha2), scale(alphax, alphabeta)), 1.0 / det));
+    let error = dot(a, a) * alpha2 + dot(b, b) * beta2
+        + 2.0 * (alphabeta * dot(a, b) - dot(a, alphax) - dot(b, betax));
+    Some((a, b, error))
+}
+
+/// try every ordered partition of colors (sorted along axis) into 4 (or 3) clusters
+fn cluster_fit(colors: &[Vec3; 16], axis: Vec3, four_color: bool) -> Option<(Vec3, Vec3)> {
+    let mut order: [usize; 16] = std::array::from_fn(|i| i);
+    order.sort_by(|&i, &j| dot(colors[i], axis).total_cmp(&dot(colors[j], axis)));
+
+    let mut prefix = [[0.0f32; 3]; 17];
+    for i in 0..16 {
+        prefix[i + 1] = add(prefix[i], colors[order[i]]);
+    }
+    let sum = |from: usize, to: usize| sub(prefix[to], prefix[from]);
+
+    let mut best: Option<(Vec3, Vec3, f32)> = None;
+    let mut consider = |candidate: Option<(Vec3, Vec3, f32)>| {
+        if let Some(c) = candidate {
+            if best.map_or(true, |b| c.2 < b.2) {
+                best = Some(c);
+            }
+        }
+    };
+
+    if four_color {
+        for i in 0..=16 {
+            for j in i..=16 {
+                for k in j..=16 {
+                    let (n0, n1, n2, n3) = (i as f32, (j - i) as f32, (k - j) as f32, (16 - k) as f32);
+                    let (x0, x1, x2, x3) = (sum(0, i), sum(i, j), sum(j, k), sum(k, 16));
+                    let alpha2 = n0 + n1 * (4.0 / 9.0) + n2 * (1.0 / 9.0);
+                    let beta2 = n3 + n2 * (4.0 / 9.0) + n1 * (1.0 / 9.0);
+                    let alphabeta = (n1 + n2) * (2.0 / 9.0);
+                    let alphax = add(add(x0, scale(x1, 2.0 / 3.0)), scale(x2, 1.0 / 3.0));
+                    let betax = add(add(x3, scale(x2, 2.0 / 3.0)), scale(x1, 1.0 / 3.0));
+                    consider(solve_clusters(alpha2, beta2, alphabeta, alphax, betax));
+                }
+            }
+        }
+    } else {
+        for i in 0..=16 {
+            for j in i..=16 {
+                let (n0, n1, n2) = (i as f32, (j - i) as f32, (16 - j) as f32);
+                let (x0, x1, x2) = (sum(0, i), sum(i, j), sum(j, 16));
+                let alpha2 = n0 + n1 * 0.25;
+                let beta2 = n2 + n1 * 0.25;
+                let alphabeta = n1 * 0.25;
+                let alphax = add(x0, scale(x1, 0.5));
+                let betax = add(x2, scale(x1, 0.5));
+                consider(solve_clusters(alpha2, beta2, alphabeta, alphax, betax));
+            }
+        }
+    }
+
+    best.map(|(a, b, _)| (a, b))
+}
+
+/// encode color part of a block
+///
+/// `allow_three_color` should be false for BC2 / BC3, where GPUs always decode color in 4 color mode
+pub(crate) fn encode_color_block(pixels: &[u32; 16], quality: BCQuality, allow_three_color: bool) -> [u8; 8] {
+    let colors: [Vec3; 16] = std::array::from_fn(|i| unpack_rgb(pixels[i]));
+    let colors_i: [[i32; 3]; 16] = std::array::from_fn(|i| colors[i].map(|c| c as i32));
+    if colors.iter().all(|&c| c == colors[0]) {
+        let (a, b) = single_color_fit(colors[0]);
+        return fit_endpoints(&colors_i, a, b, true).to_bytes();
+    }
+
+    let axis = principal_axis(&colors);
+    let (a, b) = range_fit(&colors, axis);
+    let mut best = fit_endpoints(&colors_i, a, b, true);
+
+    if quality == BCQuality::High {
+        let modes: &[bool] = if allow_three_color { &[true, false] } else { &[true] };
+        for &four_color in modes {
+            if best.error == 0 {
+                break;
+            }
+            if let Some((a, b)) = cluster_fit(&colors, axis, four_color) {
+                let candidate = fit_endpoints(&colors_i, a, b, four_color);
+                if candidate.error < best.error {
+                    best = candidate;
+                }
+            }
+        }
+    }
+
+    best.to_bytes()
+}
+
+/// encode 4x4 BGRA u32 pixels into a BC1 block
+pub fn encode_bc1_block(pixels: &[u32; 16], quality: BCQuality) -> [u8; 8] {
+    encode_color_block(pixels, quality, true)
+}
+
+/// encode BGRA u32 frame into BC1 (DXT1) data, width and height are rounded up to multiple of 4
+pub fn encode_bc1(image: &[u32], width: usize, height: usize, quality: BCQuality) -> Result<Vec<u8>, GVError> {
+    encode_blocks(image, width, height, |block| encode_bc1_block(block, quality))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{get_rgb, GVFormat, GVVideo};
+    use std::io::Cursor;
+
+    const TEST_GV: &[u8; 1547] = include_bytes!("../test_asset/test.gv");
+    const TEST_10PX_GV: &[u8; 474] = include_bytes!("../test_asset/test-10px.gv");
+
+    fn decode(data: &[u8], width: usize, height: usize) -> Vec<u32> {
+        let mut result = vec![0; width * height];
+        texture2ddecoder::decode_bc1(data, width, height, &mut result).unwrap();
+        result
+    }
+
+    /// mean squared error of rgb
+    fn rgb_mse(a: &[u32], b: &[u32]) -> f64 {
+        let sum: u64 = a.iter().zip(b).map(|(&a, &b)| {
+            let (a, b) = (get_rgb(a), get_rgb(b));
+            let dr = a.r as i64 - b.r as i64;
+            let dg = a.g as i64 - b.g as i64;
+            let db = a.b as i64 - b.b as i64;
+            (dr * dr + dg * dg + db * db) as u64
+        }).sum();
+        sum as f64 / (a.len() * 3) as f64
+    }
+
+    #[test]
+    fn solid_block() {
+        let pixels = [0xFF102030; 16];
+        for quality in [BCQuality::Fast, BCQuality::High] {
+            let block = encode_bc1_block(&pixels, quality);
+            let decoded = decode(&block, 4, 4);
+            assert!(rgb_mse(&decoded, &pixels) < 1.0);
+        }
+
+        // not representable by 565, but by interpolation
+        let pixels = [0xFF0200FF; 16];
+        let decoded = decode(&encode_bc1_block(&pixels, BCQuality::Fast), 4, 4);
+        assert_eq!(decoded, pixels);
+
+        let pixels = [0xFFFFFFFF; 16];
+        let decoded = decode(&encode_bc1_block(&pixels, BCQuality::Fast), 4, 4);
+        assert_eq!(decoded, pixels);
+    }
+
+    #[test]
+    fn two_colors_are_exact() {
+        let mut pixels = [0xFF000000; 16];
+        pixels[5..11].fill(0xFFFFFFFF);
+        for quality in [BCQuality::Fast, BCQuality::High] {
+            let decoded = decode(&encode_bc1_block(&pixels, quality), 4, 4);
+            assert_eq!(decoded, pixels);
+        }
+    }
+
+    #[test]
+    fn smpte_bar_round_trip() {
+        let mut video = GVVideo::load(Cursor::new(TEST_GV)).unwrap();
+        let (w, h) = (640, 360);
+        let frame = video.read_frame(0).unwrap();
+
+        let fast = encode_bc1(&frame, w, h, BCQuality::Fast).unwrap();
+        let high = encode_bc1(&frame, w, h, BCQuality::High).unwrap();
+        assert_eq!(fast.len(), GVFormat::DXT1.frame_bytes(w as u32, h as u32));
+        assert_eq!(high.len(), fast.len());
+
+        let fast_mse = rgb_mse(&decode(&fast, w, h), &frame);
+        let high_mse = rgb_mse(&decode(&high, w, h), &frame);
+        assert!(fast_mse < 4.0, "fast mse: {}", fast_mse);
+        assert!(high_mse <= fast_mse, "high mse: {}, fast mse: {}", high_mse, fast_mse);
+    }
+
+    #[test]
+    fn gradient_high_quality_is_better() {
+        let (w, h) = (16, 16);
+        let frame: Vec<u32> = (0..w * h).map(|i| {
+            let (x, y) = ((i % w) as u32, (i / w) as u32);
+            0xFF000000 | (x * 16) << 16 | (y * 16) << 8 | ((x + y) * 7)
+        }).collect();
+        let fast_mse = rgb_mse(&decode(&encode_bc1(&frame, w, h, BCQuality::Fast).unwrap(), w, h), &frame);
+        let high_mse = rgb_mse(&decode(&encode_bc1(&frame, w, h, BCQuality::High).unwrap(), w, h), &frame);
+        assert!(high_mse < fast_mse, "high mse: {}, fast mse: {}", high_mse, fast_mse);
+    }
+
+    #[test]
+    fn odd_dimensions() {
+        let mut video = GVVideo::load(Cursor::new(TEST_10PX_GV)).unwrap();
+        let frame = video.read_frame(0).unwrap();
+        let data = encode_bc1(&frame, 10, 10, BCQuality::High).unwrap();
+        assert_eq!(data.len(), video.get_frame_bytes() as usize);
+        assert_eq!(decode(&data, 10, 10), frame);
+    }
+}
@@ -26,6 +26,7 @@ pub fn decode_bc2_block(data: &[u8], outbuf: &mut [u32]) {
 }
 
 #[inline]
+#[allow(clippy::too_many_arguments)]
 pub fn copy_block_buffer(
     bx: usize,
     by: usize,
@@ -66,8 +67,8 @@ macro_rules! block_decoder{
                 const BLOCK_WIDTH: usize = $block_width;
                 const BLOCK_HEIGHT: usize = $block_height;
                 const BLOCK_SIZE: usize = BLOCK_WIDTH * BLOCK_HEIGHT;
-                let num_blocks_x: usize = (width + BLOCK_WIDTH - 1) / BLOCK_WIDTH;
-                let num_blocks_y: usize = (height + BLOCK_WIDTH - 1) / BLOCK_HEIGHT;
+                let num_blocks_x: usize = width.div_ceil(BLOCK_WIDTH);
+                let num_blocks_y: usize = height.div_ceil(BLOCK_HEIGHT);
                 let mut buffer: [u32; BLOCK_SIZE] = [color(0,0,0,255); BLOCK_SIZE];
 
                 if data.len() < num_blocks_x * num_blocks_y * $raw_block_size {
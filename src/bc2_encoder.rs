@@ -0,0 +1,109 @@
+// BC2 (DXT3) encoder
+//
+// explicit 4 bit alpha (8 bytes) followed by BC1 color block in 4 color mode (8 bytes),
+// inverse of decode_bc2_alpha / decode_bc2_block in bc2_decoder
+
+use crate::bc1_encoder::encode_color_block;
+use crate::encoder::{encode_blocks, BCQuality};
+use crate::GVError;
+
+/// 4x4 Bayer matrix (0..16), blocks are aligned to 4 pixels so it tiles over the frame
+const BAYER_4X4: [u8; 16] = [
+    0, 8, 2, 10,
+    12, 4, 14, 6,
+    3, 11, 1, 9,
+    15, 7, 13, 5,
+];
+
+/// quantize alpha to 4 bits (decoded as `(v << 4) | v`), optionally with ordered dithering
+pub(crate) fn encode_bc2_alpha(alphas: &[u8; 16], dither: bool) -> [u8; 8] {
+    let mut data = [0; 8];
+    for (i, &alpha) in alphas.iter().enumerate() {
+        let offset = if dither {
+            // -8.5 < offset < 8.5, spreads rounding error of one 4 bit step
+            ((BAYER_4X4[i] as f32 + 0.5) / 16.0 - 0.5) * 17.0
+        } else {
+            0.0
+        };
+        let v = ((alpha as f32 + offset) / 17.0).round().clamp(0.0, 15.0) as u8;
+        data[i / 2] |= v << ((i & 1) * 4);
+    }
+    data
+}
+
+/// encode 4x4 BGRA u32 pixels into a BC2 block
+pub fn encode_bc2_block(pixels: &[u32; 16], quality: BCQuality, dither: bool) -> [u8; 16] {
+    let alphas = pixels.map(|p| (p >> 24) as u8);
+    let mut data = [0; 16];
+    data[..8].copy_from_slice(&encode_bc2_alpha(&alphas, dither));
+    data[8..].copy_from_slice(&encode_color_block(pixels, quality, false));
+    data
+}
+
+/// encode BGRA u32 frame into BC2 (DXT3) data, width and height are rounded up to multiple of 4
+///
+/// `dither` applies 4x4 ordered dithering to alpha, which hides banding of smooth alpha gradients
+pub fn encode_bc2(image: &[u32], width: usize, height: usize, quality: BCQuality, dither: bool) -> Result<Vec<u8>, GVError> {
+    encode_blocks(image, width, height, |block| encode_bc2_block(block, quality, dither))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bc2_decoder::decode_bc2;
+    use crate::{get_alpha, GVVideo};
+    use std::io::Cursor;
+
+    // SMPTE BAR with alpha gradient
+    const TEST_ALPHA_GV: &[u8; 4857] = include_bytes!("../test_asset/test-alpha.gv");
+
+    fn decode(data: &[u8], width: usize, height: usize) -> Vec<u32> {
+        let mut result = vec![0; width * height];
+        decode_bc2(data, width, height, &mut result).unwrap();
+        result
+    }
+
+    #[test]
+    fn alpha_steps_are_exact() {
+        let pixels: [u32; 16] = std::array::from_fn(|i| ((i as u32 * 17) << 24) | 0x00406080);
+        for dither in [false, true] {
+            let decoded = decode(&encode_bc2_block(&pixels, BCQuality::Fast, dither), 4, 4);
+            for (d, p) in decoded.iter().zip(&pixels) {
+                assert_eq!(get_alpha(*d), get_alpha(*p));
+            }
+        }
+    }
+
+    #[test]
+    fn dither_keeps_average_alpha() {
+        // alpha 8 is halfway between 4 bit steps 0 and 17
+        let pixels = [0x08FFFFFF; 16];
+        let plain = decode(&encode_bc2_block(&pixels, BCQuality::Fast, false), 4, 4);
+        let dithered = decode(&encode_bc2_block(&pixels, BCQuality::Fast, true), 4, 4);
+        let average = |frame: &[u32]| frame.iter().map(|&p| get_alpha(p) as f32).sum::<f32>() / 16.0;
+        assert!((average(&plain) - 8.0).abs() > 4.0);
+        assert!((average(&dithered) - 8.0).abs() <= 1.5, "average: {}", average(&dithered));
+    }
+
+    #[test]
+    fn alpha_gradient_round_trip() {
+        let mut source = GVVideo::load(Cursor::new(TEST_ALPHA_GV)).unwrap();
+        let (w, h) = source.get_resolution();
+        let (w, h) = (w as usize, h as usize);
+        let frame = source.read_frame(0).unwrap();
+
+        for dither in [false, true] {
+            let data = encode_bc2(&frame, w, h, BCQuality::Fast, dither).unwrap();
+            assert_eq!(data.len(), w.div_ceil(4) * h.div_ceil(4) * 16);
+            let decoded = decode(&data, w, h);
+
+            let max_error = decoded.iter().zip(&frame)
+                .map(|(&d, &f)| (get_alpha(d) as i32 - get_alpha(f) as i32).abs())
+                .max()
+                .unwrap();
+            // half of a 4 bit step without dithering, one step with dithering
+            let limit = if dither { 17 } else { 9 };
+            assert!(max_error <= limit, "dither: {}, max error: {}", dither, max_error);
+        }
+    }
+}
@@ -0,0 +1,176 @@
+// BC3 (DXT5) encoder
+//
+// interpolated alpha block (8 bytes) followed by BC1 color block in 4 color mode (8 bytes),
+// alpha palettes are evaluated exactly as texture2ddecoder::decode_bc3_alpha reconstructs them
+
+use crate::bc1_encoder::encode_color_block;
+use crate::encoder::{encode_blocks, BCQuality};
+use crate::GVError;
+
+/// same palette as texture2ddecoder's decode_bc3_alpha
+#[inline]
+fn alpha_palette(a0: u8, a1: u8) -> [u8; 8] {
+    let (a0, a1) = (a0 as u16, a1 as u16);
+    let mut a = [a0, a1, 0, 0, 0, 0, 0, 0];
+    if a0 > a1 {
+        for (i, v) in a.iter_mut().enumerate().skip(2) {
+            let w = i as u16 - 1;
+            *v = (a0 * (7 - w) + a1 * w) / 7;
+        }
+    } else {
+        for (i, v) in a.iter_mut().enumerate().take(6).skip(2) {
+            let w = i as u16 - 1;
+            *v = (a0 * (5 - w) + a1 * w) / 5;
+        }
+        a[6] = 0;
+        a[7] = 255;
+    }
+    a.map(|v| v as u8)
+}
+
+/// pick nearest palette entry for each alpha, returns (48 bit indices, squared error)
+fn fit_alpha(alphas: &[u8; 16], a0: u8, a1: u8) -> (u64, u32) {
+    let palette = alpha_palette(a0, a1);
+    let mut indices = 0;
+    let mut error = 0;
+    for (i, &alpha) in alphas.iter().enumerate() {
+        let (index, e) = palette
+            .iter()
+            .enumerate()
+            .map(|(index, &p)| (index as u64, (p as i32 - alpha as i32).pow(2) as u32))
+            .min_by_key(|&(_, e)| e)
+            .unwrap();
+        indices |= index << (i * 3);
+        error += e;
+    }
+    (indices, error)
+}
+
+#[inline]
+fn alpha_block_bytes(a0: u8, a1: u8, indices: u64) -> [u8; 8] {
+    let mut data = [0; 8];
+    data[0] = a0;
+    data[1] = a1;
+    data[2..8].copy_from_slice(&indices.to_le_bytes()[..6]);
+    data
+}
+
+/// encode 16 alpha values into an interpolated (BC3 / BC4 style) alpha block
+pub(crate) fn encode_alpha_block(alphas: &[u8; 16], quality: BCQuality) -> [u8; 8] {
+    let min = *alphas.iter().min().unwrap();
+    let max = *alphas.iter().max().unwrap();
+
+    // 8 alpha mode over full range (or single value when min == max)
+    let (indices, mut best_error) = fit_alpha(alphas, max, min);
+    let mut best = (max, min, indices);
+
+    if quality == BCQuality::High && best_error > 0 {
+        // 6 alpha mode with explicit 0 and 255, endpoints cover only the remaining values
+        let inner = alphas.iter().copied().filter(|&a| a != 0 && a != 255);
+        if let (Some(min6), Some(max6)) = (inner.clone().min(), inner.max()) {
+            let (indices, error) = fit_alpha(alphas, min6, max6);
+            if error < best_error {
+                best_error = error;
+                best = (min6, max6, indices);
+            }
+        }
+
+        // shrink 8 alpha mode range, interpolated entries may land closer to the values
+        for a0 in max.saturating_sub(8).max(min)..=max {
+            for a1 in min..=min.saturating_add(8).min(a0) {
+                if a0 == a1 {
+                    continue;
+                }
+                let (indices, error) = fit_alpha(alphas, a0, a1);
+                if error < best_error {
+                    best_error = error;
+                    best = (a0, a1, indices);
+                }
+            }
+        }
+    }
+
+    alpha_block_bytes(best.0, best.1, best.2)
+}
+
+/// encode 4x4 BGRA u32 pixels into a BC3 block
+pub fn encode_bc3_block(pixels: &[u32; 16], quality: BCQuality) -> [u8; 16] {
+    let alphas = pixels.map(|p| (p >> 24) as u8);
+    let mut data = [0; 16];
+    data[..8].copy_from_slice(&encode_alpha_block(&alphas, quality));
+    data[8..].copy_from_slice(&encode_color_block(pixels, quality, false));
+    data
+}
+
+/// encode BGRA u32 frame into BC3 (DXT5) data, width and height are rounded up to multiple of 4
+pub fn encode_bc3(image: &[u32], width: usize, height: usize, quality: BCQuality) -> Result<Vec<u8>, GVError> {
+    encode_blocks(image, width, height, |block| encode_bc3_block(block, quality))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{get_alpha, GVFormat, GVVideo, GVWriter};
+    use std::io::Cursor;
+
+    // SMPTE BAR with alpha gradient
+    const TEST_ALPHA_GV: &[u8; 4857] = include_bytes!("../test_asset/test-alpha.gv");
+
+    fn alpha_mse(a: &[u32], b: &[u32]) -> f64 {
+        let sum: u64 = a.iter().zip(b).map(|(&a, &b)| (get_alpha(a) as i64 - get_alpha(b) as i64).pow(2) as u64).sum();
+        sum as f64 / a.len() as f64
+    }
+
+    #[test]
+    fn alpha_palette_matches_decoder() {
+        for (a0, a1) in [(255, 0), (0, 255), (200, 13), (13, 200), (77, 77)] {
+            let palette = alpha_palette(a0, a1);
+            for (index, &expected) in palette.iter().enumerate() {
+                let mut block = [0u8; 16];
+                block[0] = a0;
+                block[1] = a1;
+                // pixel 0 uses index
+                block[2] = index as u8;
+                let mut out = [0u32; 16];
+                texture2ddecoder::decode_bc3(&block, 4, 4, &mut out).unwrap();
+                assert_eq!(get_alpha(out[0]), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn alpha_extremes_are_exact() {
+        let mut pixels = [0x00FF0000; 16];
+        pixels[3..9].fill(0xFFFF0000);
+        pixels[12] = 0x80FF0000;
+        let block = encode_bc3_block(&pixels, BCQuality::High);
+        let mut out = [0u32; 16];
+        texture2ddecoder::decode_bc3(&block, 4, 4, &mut out).unwrap();
+        assert_eq!(out, pixels);
+    }
+
+    #[test]
+    fn alpha_gradient_round_trip() {
+        let mut source = GVVideo::load(Cursor::new(TEST_ALPHA_GV)).unwrap();
+        let (w, h) = source.get_resolution();
+        let frame = source.read_frame(0).unwrap();
+
+        let mut mse = Vec::new();
+        for quality in [BCQuality::Fast, BCQuality::High] {
+            let data = encode_bc3(&frame, w as usize, h as usize, quality).unwrap();
+            assert_eq!(data.len(), GVFormat::DXT5.frame_bytes(w, h));
+
+            // decode through GVVideo::read_frame
+            let mut writer = GVWriter::new(Cursor::new(Vec::new()), w, h, 30.0, GVFormat::DXT5).unwrap();
+            writer.write_frame(&data).unwrap();
+            let written = writer.finish().unwrap().into_inner();
+            let mut video = GVVideo::load(Cursor::new(written)).unwrap();
+            let decoded = video.read_frame(0).unwrap();
+
+            let alpha = alpha_mse(&decoded, &frame);
+            assert!(alpha < 1.0, "{:?} alpha mse: {}", quality, alpha);
+            mse.push(alpha);
+        }
+        assert!(mse[1] <= mse[0]);
+    }
+}
@@ -0,0 +1,743 @@
+// BC7 encoder
+//
+// every enabled mode / partition / rotation is fitted with a range fit along the principal axis, a p-bit search
+// and optional least squares refinement, then the candidate with the lowest error is packed.
+// palettes are evaluated exactly as texture2ddecoder::decode_bc7_block reconstructs them.
+//
+// partition, anchor and weight tables from texture2ddecoder, dual-licensed under Apache 2.0 and MIT, by @UniversalGameExtraction
+// see https://github.com/UniversalGameExtraction/texture2ddecoder
+
+use std::ops::Range;
+
+use crate::encoder::encode_blocks_parallel;
+use crate::GVError;
+
+/// speed / quality preset for BC7
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum BC7Quality {
+    /// mode 6 only
+    Fast,
+    /// modes 1, 5, 6, 7 with the 8 most promising partitions
+    #[default]
+    Normal,
+    /// all modes, partitions and rotations
+    High,
+}
+
+/// BC7 encoder settings, usually created from a preset with `BC7Options::new(quality)`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct BC7Options {
+    /// bit mask of modes to try (bit n = mode n), mode 6 is used when empty
+    pub modes: u8,
+    /// number of partitions (most promising first) tried in 2 and 3 subset modes
+    pub partitions: usize,
+    /// least squares refinement passes of endpoints
+    pub refine_passes: usize,
+    /// try every rotation of mode 4 / 5 and both index selections of mode 4
+    pub rotations: bool,
+    /// encoder threads, 0 uses std::thread::available_parallelism
+    pub threads: usize,
+}
+
+impl BC7Options {
+    pub fn new(quality: BC7Quality) -> BC7Options {
+        match quality {
+            BC7Quality::Fast => BC7Options {
+                modes: 1 << 6,
+                partitions: 0,
+                refine_passes: 0,
+                rotations: false,
+                threads: 0,
+            },
+            BC7Quality::Normal => BC7Options {
+                modes: (1 << 1) | (1 << 5) | (1 << 6) | (1 << 7),
+                partitions: 8,
+                refine_passes: 1,
+                rotations: false,
+                threads: 0,
+            },
+            BC7Quality::High => BC7Options {
+                modes: 0xFF,
+                partitions: 64,
+                refine_passes: 2,
+                rotations: true,
+                threads: 0,
+            },
+        }
+    }
+}
+
+impl Default for BC7Options {
+    fn default() -> Self {
+        BC7Options::new(BC7Quality::default())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum PBits {
+    None,
+    /// one p-bit per endpoint
+    Unique,
+    /// one p-bit per subset
+    Shared,
+}
+
+struct ModeInfo {
+    subsets: usize,
+    partition_bits: usize,
+    rotation_bits: usize,
+    index_selection_bits: usize,
+    color_bits: usize,
+    alpha_bits: usize,
+    pbits: PBits,
+    index_bits: [usize; 2],
+}
+
+const MODES: [ModeInfo; 8] = [
+    ModeInfo { subsets: 3, partition_bits: 4, rotation_bits: 0, index_selection_bits: 0, color_bits: 4, alpha_bits: 0, pbits: PBits::Unique, index_bits: [3, 0] },
+    ModeInfo { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 6, alpha_bits: 0, pbits: PBits::Shared, index_bits: [3, 0] },
+    ModeInfo { subsets: 3, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 5, alpha_bits: 0, pbits: PBits::None, index_bits: [2, 0] },
+    ModeInfo { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 7, alpha_bits: 0, pbits: PBits::Unique, index_bits: [2, 0] },
+    ModeInfo { subsets: 1, partition_bits: 0, rotation_bits: 2, index_selection_bits: 1, color_bits: 5, alpha_bits: 6, pbits: PBits::None, index_bits: [2, 3] },
+    ModeInfo { subsets: 1, partition_bits: 0, rotation_bits: 2, index_selection_bits: 0, color_bits: 7, alpha_bits: 8, pbits: PBits::None, index_bits: [2, 2] },
+    ModeInfo { subsets: 1, partition_bits: 0, rotation_bits: 0, index_selection_bits: 0, color_bits: 7, alpha_bits: 7, pbits: PBits::Unique, index_bits: [4, 0] },
+    ModeInfo { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 5, alpha_bits: 5, pbits: PBits::Unique, index_bits: [2, 0] },
+];
+
+/// partition of 2 subset modes, bit i = subset of pixel i
+const PARTITIONS2: [u16; 64] = [
+    0xcccc, 0x8888, 0xeeee, 0xecc8, 0xc880, 0xfeec, 0xfec8, 0xec80,
+    0xc800, 0xffec, 0xfe80, 0xe800, 0xffe8, 0xff00, 0xfff0, 0xf000,
+    0xf710, 0x008e, 0x7100, 0x08ce, 0x008c, 0x7310, 0x3100, 0x8cce,
+    0x088c, 0x3110, 0x6666, 0x366c, 0x17e8, 0x0ff0, 0x718e, 0x399c,
+    0xaaaa, 0xf0f0, 0x5a5a, 0x33cc, 0x3c3c, 0x55aa, 0x9696, 0xa55a,
+    0x73ce, 0x13c8, 0x324c, 0x3bdc, 0x6996, 0xc33c, 0x9966, 0x0660,
+    0x0272, 0x04e4, 0x4e40, 0x2720, 0xc936, 0x936c, 0x39c6, 0x639c,
+    0x9336, 0x9cc6, 0x817e, 0xe718, 0xccf0, 0x0fcc, 0x7744, 0xee22,
+];
+
+/// partition of 3 subset modes, bits 2i..2i+1 = subset of pixel i
+const PARTITIONS3: [u32; 64] = [
+    0xaa685050, 0x6a5a5040, 0x5a5a4200, 0x5450a0a8, 0xa5a50000, 0xa0a05050, 0x5555a0a0, 0x5a5a5050,
+    0xaa550000, 0xaa555500, 0xaaaa5500, 0x90909090, 0x94949494, 0xa4a4a4a4, 0xa9a59450, 0x2a0a4250,
+    0xa5945040, 0x0a425054, 0xa5a5a500, 0x55a0a0a0, 0xa8a85454, 0x6a6a4040, 0xa4a45000, 0x1a1a0500,
+    0x0050a4a4, 0xaaa59090, 0x14696914, 0x69691400, 0xa08585a0, 0xaa821414, 0x50a4a450, 0x6a5a0200,
+    0xa9a58000, 0x5090a0a8, 0xa8a09050, 0x24242424, 0x00aa5500, 0x24924924, 0x24499224, 0x50a50a50,
+    0x500aa550, 0xaaaa4444, 0x66660000, 0xa5a0a5a0, 0x50a050a0, 0x69286928, 0x44aaaa44, 0x66666600,
+    0xaa444444, 0x54a854a8, 0x95809580, 0x96969600, 0xa85454a8, 0x80959580, 0xaa141414, 0x96960000,
+    0xaaaa1414, 0xa05050a0, 0xa0a5a5a0, 0x96000000, 0x40804080, 0xa9a8a9a8, 0xaaaaaa44, 0x2a4a5254,
+];
+
+/// anchor pixel of subset 1 in 2 subset modes
+const ANCHORS2: [usize; 64] = [
+    15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15,
+    15, 2, 8, 2, 2, 8, 8, 15, 2, 8, 2, 2, 8, 8, 2, 2,
+    15, 15, 6, 8, 2, 8, 15, 15, 2, 8, 2, 2, 2, 15, 15, 6,
+    6, 2, 6, 8, 15, 15, 2, 2, 15, 15, 15, 15, 15, 2, 2, 15,
+];
+
+/// anchor pixels of subset 1 and 2 in 3 subset modes
+const ANCHORS3: [[usize; 64]; 2] = [
+    [
+        3, 3, 15, 15, 8, 3, 15, 15, 8, 8, 6, 6, 6, 5, 3, 3,
+        3, 3, 8, 15, 3, 3, 6, 10, 5, 8, 8, 6, 8, 5, 15, 15,
+        8, 15, 3, 5, 6, 10, 8, 15, 15, 3, 15, 5, 15, 15, 15, 15,
+        3, 15, 5, 5, 5, 8, 5, 10, 5, 10, 8, 13, 15, 12, 3, 3,
+    ],
+    [
+        15, 8, 8, 3, 15, 15, 3, 8, 15, 15, 15, 15, 15, 15, 15, 8,
+        15, 8, 15, 3, 15, 8, 15, 8, 3, 15, 6, 10, 15, 15, 10, 8,
+        15, 3, 15, 10, 10, 8, 9, 10, 6, 15, 8, 15, 3, 6, 6, 8,
+        15, 3, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 3, 15, 15, 8,
+    ],
+];
+
+/// interpolation weights (out of 64) for 2, 3, 4 bit indices
+const WEIGHTS: [&[u8]; 3] = [
+    &[0, 21, 43, 64],
+    &[0, 9, 18, 27, 37, 46, 55, 64],
+    &[0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64],
+];
+
+/// RGBA
+type Pixel = [i32; 4];
+
+/// same as texture2ddecoder's expand_quantized
+#[inline]
+fn expand(v: u8, bits: usize) -> i32 {
+    if bits >= 8 {
+        return v as i32;
+    }
+    let s = ((v as u32) << (8 - bits)) & 0xFF;
+    (s | (s >> bits)) as i32
+}
+
+/// expanded 8 bit value of a stored endpoint value (and its p-bit)
+#[inline]
+fn unquantize(q: u8, bits: usize, pbit: Option<u8>) -> i32 {
+    match pbit {
+        Some(p) => expand((q << 1) | p, bits + 1),
+        None => expand(q, bits),
+    }
+}
+
+/// nearest stored endpoint value of v with given bits (and p-bit)
+fn quantize(v: f32, bits: usize, pbit: Option<u8>) -> u8 {
+    let total_bits = bits + pbit.is_some() as usize;
+    let guess = (v.clamp(0.0, 255.0) / 255.0 * ((1 << total_bits) - 1) as f32).round() as i32;
+    let guess = if pbit.is_some() { guess >> 1 } else { guess };
+    let max = (1 << bits) - 1;
+    let mut best = (0, i32::MAX);
+    for q in (guess - 1).max(0)..=(guess + 1).min(max) {
+        let error = (unquantize(q as u8, bits, pbit) as f32 - v).abs() as i32;
+        if error < best.1 {
+            best = (q as u8, error);
+        }
+    }
+    best.0
+}
+
+#[inline]
+fn interpolate(e0: i32, e1: i32, weight: i32) -> i32 {
+    (e0 * (64 - weight) + e1 * weight + 32) >> 6
+}
+
+/// BGRA u32 -> RGBA
+#[inline]
+fn unpack_rgba(color: u32) -> Pixel {
+    [((color >> 16) & 0xFF) as i32, ((color >> 8) & 0xFF) as i32, (color & 0xFF) as i32, (color >> 24) as i32]
+}
+
+/// swap alpha with red (1), green (2) or blue (3), as the decoder does after interpolation
+#[inline]
+fn rotate(pixels: &[Pixel; 16], rotation: usize) -> [Pixel; 16] {
+    let mut pixels = *pixels;
+    if rotation > 0 {
+        for p in pixels.iter_mut() {
+            p.swap(3, rotation - 1);
+        }
+    }
+    pixels
+}
+
+#[inline]
+fn subset_of(subsets: usize, partition: usize, pixel: usize) -> usize {
+    match subsets {
+        2 => ((PARTITIONS2[partition] >> pixel) & 1) as usize,
+        3 => ((PARTITIONS3[partition] >> (pixel * 2)) & 3) as usize,
+        _ => 0,
+    }
+}
+
+#[inline]
+fn anchor_of(subsets: usize, partition: usize, subset: usize) -> usize {
+    match (subsets, subset) {
+        (_, 0) => 0,
+        (2, _) => ANCHORS2[partition],
+        (_, s) => ANCHORS3[s - 1][partition],
+    }
+}
+
+/// mean and principal axis (unit length) of members over channels, with variance along the axis and in total
+fn principal_axis(pixels: &[Pixel; 16], members: &[usize], channels: &Range<usize>) -> ([f32; 4], [f32; 4], f32, f32) {
+    let n = members.len().max(1) as f32;
+    let mut mean = [0.0f32; 4];
+    for &m in members {
+        for c in channels.clone() {
+            mean[c] += pixels[m][c] as f32 / n;
+        }
+    }
+
+    let mut cov = [[0.0f32; 4]; 4];
+    for &m in members {
+        for i in channels.clone() {
+            for j in channels.clone() {
+                cov[i][j] += (pixels[m][i] as f32 - mean[i]) * (pixels[m][j] as f32 - mean[j]);
+            }
+        }
+    }
+    let total: f32 = channels.clone().map(|c| cov[c][c]).sum();
+
+    // start from the row of largest variance
+    let row = channels.clone().fold(channels.start, |best, c| if cov[c][c] > cov[best][best] { c } else { best });
+    let mut axis = cov[row];
+    let mut variance = 0.0;
+    for _ in 0..8 {
+        let len = axis.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if len <= f32::EPSILON {
+            return (mean, [0.0; 4], 0.0, total);
+        }
+        axis = axis.map(|v| v / len);
+        let mut next = [0.0f32; 4];
+        for i in channels.clone() {
+            next[i] = channels.clone().map(|j| cov[i][j] * axis[j]).sum();
+        }
+        variance = channels.clone().map(|c| next[c] * axis[c]).sum();
+        axis = next;
+    }
+    let len = axis.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if len > f32::EPSILON {
+        axis = axis.map(|v| v / len);
+    }
+    (mean, axis, variance, total)
+}
+
+/// pixels of one subset fitted with one set of endpoints and indices
+struct Group<'a> {
+    pixels: &'a [Pixel; 16],
+    members: &'a [usize],
+    channels: Range<usize>,
+    /// stored bits per channel (without p-bit)
+    bits: [usize; 4],
+    pbits: PBits,
+    index_bits: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct GroupFit {
+    /// stored endpoint values (without p-bit)
+    endpoints: [[u8; 4]; 2],
+    pbits: [u8; 2],
+    /// index per pixel, only members are valid
+    indices: [u8; 16],
+    error: u32,
+}
+
+impl Group<'_> {
+    fn fit(&self, refine_passes: usize) -> GroupFit {
+        let (mean, axis, _, _) = principal_axis(self.pixels, self.members, &self.channels);
+        let (mut min, mut max) = (0.0f32, 0.0f32);
+        for &m in self.members {
+            let t: f32 = self.channels.clone().map(|c| (self.pixels[m][c] as f32 - mean[c]) * axis[c]).sum();
+            min = min.min(t);
+            max = max.max(t);
+        }
+        let e0 = std::array::from_fn(|c| mean[c] + axis[c] * min);
+        let e1 = std::array::from_fn(|c| mean[c] + axis[c] * max);
+
+        let mut best = self.quantize_endpoints(e0, e1);
+        for _ in 0..refine_passes {
+            if best.error == 0 {
+                break;
+            }
+            match self.least_squares(&best.indices) {
+                Some((e0, e1)) => {
+                    let candidate = self.quantize_endpoints(e0, e1);
+                    if candidate.error >= best.error {
+                        break;
+                    }
+                    best = candidate;
+                }
+                None => break,
+            }
+        }
+        best
+    }
+
+    /// quantize float endpoints with every p-bit combination and keep the best
+    fn quantize_endpoints(&self, e0: [f32; 4], e1: [f32; 4]) -> GroupFit {
+        let combos: &[[u8; 2]] = match self.pbits {
+            PBits::None => &[[0, 0]],
+            PBits::Unique => &[[0, 0], [0, 1], [1, 0], [1, 1]],
+            PBits::Shared => &[[0, 0], [1, 1]],
+        };
+        let mut best: Option<GroupFit> = None;
+        for &pbits in combos {
+            let pbit = |i: usize| if self.pbits == PBits::None { None } else { Some(pbits[i]) };
+            let mut endpoints = [[0u8; 4]; 2];
+            for c in self.channels.clone() {
+                endpoints[0][c] = quantize(e0[c], self.bits[c], pbit(0));
+                endpoints[1][c] = quantize(e1[c], self.bits[c], pbit(1));
+            }
+            let (indices, error) = self.fit_indices(&endpoints, pbits);
+            if best.map_or(true, |b| error < b.error) {
+                best = Some(GroupFit { endpoints, pbits, indices, error });
+            }
+        }
+        best.unwrap()
+    }
+
+    fn expanded(&self, endpoints: &[[u8; 4]; 2], pbits: [u8; 2]) -> [Pixel; 2] {
+        let pbit = |i: usize| if self.pbits == PBits::None { None } else { Some(pbits[i]) };
+        let mut expanded = [[0; 4]; 2];
+        for c in self.channels.clone() {
+            expanded[0][c] = unquantize(endpoints[0][c], self.bits[c], pbit(0));
+            expanded[1][c] = unquantize(endpoints[1][c], self.bits[c], pbit(1));
+        }
+        expanded
+    }
+
+    fn fit_indices(&self, endpoints: &[[u8; 4]; 2], pbits: [u8; 2]) -> ([u8; 16], u32) {
+        let [e0, e1] = self.expanded(endpoints, pbits);
+        let weights = WEIGHTS[self.index_bits - 2];
+        let mut palette = [[0i32; 4]; 16];
+        for (entry, &w) in palette.iter_mut().zip(weights) {
+            for c in self.channels.clone() {
+                entry[c] = interpolate(e0[c], e1[c], w as i32);
+            }
+        }
+
+        let mut indices = [0u8; 16];
+        let mut error = 0;
+        for &m in self.members {
+            let p = &self.pixels[m];
+            let mut best = (0, u32::MAX);
+            for (index, entry) in palette[..weights.len()].iter().enumerate() {
+                let e: i32 = self.channels.clone().map(|c| (p[c] - entry[c]).pow(2)).sum();
+                if (e as u32) < best.1 {
+                    best = (index as u8, e as u32);
+                }
+            }
+            indices[m] = best.0;
+            error += best.1;
+        }
+        (indices, error)
+    }
+
+    /// endpoints minimizing squared error for fixed indices, None when all members share one index
+    fn least_squares(&self, indices: &[u8; 16]) -> Option<([f32; 4], [f32; 4])> {
+        let weights = WEIGHTS[self.index_bits - 2];
+        let (mut aa, mut ab, mut bb) = (0.0f32, 0.0f32, 0.0f32);
+        let (mut ax, mut bx) = ([0.0f32; 4], [0.0f32; 4]);
+        for &m in self.members {
+            let b = weights[indices[m] as usize] as f32 / 64.0;
+            let a = 1.0 - b;
+            aa += a * a;
+            ab += a * b;
+            bb += b * b;
+            for c in self.channels.clone() {
+                ax[c] += a * self.pixels[m][c] as f32;
+                bx[c] += b * self.pixels[m][c] as f32;
+            }
+        }
+        let det = aa * bb - ab * ab;
+        if det.abs() < 1e-6 {
+            return None;
+        }
+        let e0 = std::array::from_fn(|c| (ax[c] * bb - bx[c] * ab) / det);
+        let e1 = std::array::from_fn(|c| (bx[c] * aa - ax[c] * ab) / det);
+        Some((e0, e1))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Candidate {
+    mode: usize,
+    partition: usize,
+    rotation: usize,
+    index_selection: usize,
+    /// stored endpoint values per subset (without p-bit)
+    endpoints: [[[u8; 4]; 2]; 3],
+    pbits: [[u8; 2]; 3],
+    /// index sets (second set only for mode 4 / 5)
+    indices: [[u8; 16]; 2],
+    error: u32,
+}
+
+fn encode_candidate(pixels: &[Pixel; 16], mode: usize, partition: usize, rotation: usize, index_selection: usize, refine_passes: usize) -> Candidate {
+    let mi = &MODES[mode];
+    let pixels = rotate(pixels, rotation);
+    let bits = [mi.color_bits, mi.color_bits, mi.color_bits, mi.alpha_bits];
+    let mut candidate = Candidate {
+        mode,
+        partition,
+        rotation,
+        index_selection,
+        endpoints: [[[0; 4]; 2]; 3],
+        pbits: [[0; 2]; 3],
+        indices: [[0; 16]; 2],
+        error: 0,
+    };
+
+    if mi.index_bits[1] == 0 {
+        // color and alpha share indices
+        let channels = if mi.alpha_bits > 0 { 0..4 } else { 0..3 };
+        let index_bits = mi.index_bits[0];
+        for subset in 0..mi.subsets {
+            let members: Vec<usize> = (0..16).filter(|&i| subset_of(mi.subsets, partition, i) == subset).collect();
+            let group = Group { pixels: &pixels, members: &members, channels: channels.clone(), bits, pbits: mi.pbits, index_bits };
+            let mut fit = group.fit(refine_passes);
+
+            // anchor index must have its most significant bit cleared
+            let anchor = anchor_of(mi.subsets, partition, subset);
+            if fit.indices[anchor] >> (index_bits - 1) != 0 {
+                fit.endpoints.swap(0, 1);
+                fit.pbits.swap(0, 1);
+                for &m in &members {
+                    fit.indices[m] = ((1 << index_bits) - 1) - fit.indices[m];
+                }
+            }
+
+            candidate.endpoints[subset] = fit.endpoints;
+            candidate.pbits[subset] = fit.pbits;
+            for &m in &members {
+                candidate.indices[0][m] = fit.indices[m];
+            }
+            candidate.error += fit.error;
+        }
+        if mi.alpha_bits == 0 {
+            candidate.error += pixels.iter().map(|p| (255 - p[3]).pow(2) as u32).sum::<u32>();
+        }
+    } else {
+        // separate color and alpha indices, single subset without p-bits
+        let members: Vec<usize> = (0..16).collect();
+        let groups = [(0..3, index_selection), (3..4, 1 - index_selection)];
+        for (channels, set) in groups {
+            let index_bits = mi.index_bits[set];
+            let group = Group { pixels: &pixels, members: &members, channels: channels.clone(), bits, pbits: PBits::None, index_bits };
+            let mut fit = group.fit(refine_passes);
+
+            if fit.indices[0] >> (index_bits - 1) != 0 {
+                fit.endpoints.swap(0, 1);
+                for index in fit.indices.iter_mut() {
+                    *index = ((1 << index_bits) - 1) - *index;
+                }
+            }
+
+            for c in channels {
+                candidate.endpoints[0][0][c] = fit.endpoints[0][c];
+                candidate.endpoints[0][1][c] = fit.endpoints[1][c];
+            }
+            candidate.indices[set] = fit.indices;
+            candidate.error += fit.error;
+        }
+    }
+
+    candidate
+}
+
+/// partitions of mode, most promising (least variance off the principal axes) first
+fn rank_partitions(pixels: &[Pixel; 16], mode: usize, count: usize) -> Vec<usize> {
+    let mi = &MODES[mode];
+    let partitions = 1 << mi.partition_bits;
+    if count >= partitions {
+        return (0..partitions).collect();
+    }
+
+    let channels = if mi.alpha_bits > 0 { 0..4 } else { 0..3 };
+    let mut scored: Vec<(f32, usize)> = (0..partitions).map(|partition| {
+        let score = (0..mi.subsets).map(|subset| {
+            let members: Vec<usize> = (0..16).filter(|&i| subset_of(mi.subsets, partition, i) == subset).collect();
+            let (_, _, variance, total) = principal_axis(pixels, &members, &channels);
+            total - variance
+        }).sum();
+        (score, partition)
+    }).collect();
+    scored.sort_by(|a, b| a.0.total_cmp(&b.0));
+    scored.into_iter().take(count.max(1)).map(|(_, partition)| partition).collect()
+}
+
+struct BitWriter {
+    bits: u128,
+    pos: usize,
+}
+
+impl BitWriter {
+    #[inline]
+    fn write(&mut self, value: u32, count: usize) {
+        self.bits |= (value as u128) << self.pos;
+        self.pos += count;
+    }
+}
+
+fn pack(c: &Candidate) -> [u8; 16] {
+    let mi = &MODES[c.mode];
+    let mut w = BitWriter { bits: 0, pos: 0 };
+    w.write(1 << c.mode, c.mode + 1);
+    w.write(c.partition as u32, mi.partition_bits);
+    w.write(c.rotation as u32, mi.rotation_bits);
+    w.write(c.index_selection as u32, mi.index_selection_bits);
+
+    for channel in 0..3 {
+        for subset in 0..mi.subsets {
+            w.write(c.endpoints[subset][0][channel] as u32, mi.color_bits);
+            w.write(c.endpoints[subset][1][channel] as u32, mi.color_bits);
+        }
+    }
+    if mi.alpha_bits > 0 {
+        for subset in 0..mi.subsets {
+            w.write(c.endpoints[subset][0][3] as u32, mi.alpha_bits);
+            w.write(c.endpoints[subset][1][3] as u32, mi.alpha_bits);
+        }
+    }
+    for subset in 0..mi.subsets {
+        match mi.pbits {
+            PBits::Unique => {
+                w.write(c.pbits[subset][0] as u32, 1);
+                w.write(c.pbits[subset][1] as u32, 1);
+            }
+            PBits::Shared => w.write(c.pbits[subset][0] as u32, 1),
+            PBits::None => {}
+        }
+    }
+
+    for (set, &index_bits) in mi.index_bits.iter().enumerate() {
+        if index_bits == 0 {
+            continue;
+        }
+        for i in 0..16 {
+            let is_anchor = (0..mi.subsets).any(|subset| anchor_of(mi.subsets, c.partition, subset) == i);
+            w.write(c.indices[set][i] as u32, index_bits - is_anchor as usize);
+        }
+    }
+
+    debug_assert_eq!(w.pos, 128);
+    w.bits.to_le_bytes()
+}
+
+/// encode block and return it with its squared error (over RGBA)
+fn encode_block_with_error(pixels: &[u32; 16], options: &BC7Options) -> ([u8; 16], u32) {
+    let pixels = pixels.map(unpack_rgba);
+    let modes = if options.modes == 0 { 1 << 6 } else { options.modes };
+
+    let mut best: Option<Candidate> = None;
+    'modes: for (mode, mi) in MODES.iter().enumerate() {
+        if modes & (1 << mode) == 0 {
+            continue;
+        }
+        let rotations = if mi.rotation_bits > 0 && options.rotations { 4 } else { 1 };
+        let index_selections = if mi.index_selection_bits > 0 && options.rotations { 2 } else { 1 };
+        let partitions = if mi.subsets > 1 { rank_partitions(&pixels, mode, options.partitions) } else { vec![0] };
+
+        for &partition in &partitions {
+            for rotation in 0..rotations {
+                for index_selection in 0..index_selections {
+                    let candidate = encode_candidate(&pixels, mode, partition, rotation, index_selection, options.refine_passes);
+                    if best.map_or(true, |b| candidate.error < b.error) {
+                        best = Some(candidate);
+                    }
+                    if candidate.error == 0 {
+                        break 'modes;
+                    }
+                }
+            }
+        }
+    }
+
+    let best = best.unwrap();
+    (pack(&best), best.error)
+}
+
+/// encode 4x4 BGRA u32 pixels into a BC7 block
+pub fn encode_bc7_block(pixels: &[u32; 16], options: &BC7Options) -> [u8; 16] {
+    encode_block_with_error(pixels, options).0
+}
+
+/// encode BGRA u32 frame into BC7 data on `options.threads` threads, width and height are rounded up to multiple of 4
+pub fn encode_bc7(image: &[u32], width: usize, height: usize, options: &BC7Options) -> Result<Vec<u8>, GVError> {
+    encode_blocks_parallel(image, width, height, options.threads, |block| encode_bc7_block(block, options))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GVFormat, GVVideo};
+    use std::io::Cursor;
+
+    const TEST_GV: &[u8; 1547] = include_bytes!("../test_asset/test.gv");
+    const TEST_ALPHA_GV: &[u8; 4857] = include_bytes!("../test_asset/test-alpha.gv");
+
+    fn decode(data: &[u8], width: usize, height: usize) -> Vec<u32> {
+        let mut result = vec![0; width * height];
+        texture2ddecoder::decode_bc7(data, width, height, &mut result).unwrap();
+        result
+    }
+
+    fn squared_error(a: &[u32], b: &[u32]) -> u32 {
+        a.iter().zip(b).map(|(&a, &b)| {
+            let (a, b) = (unpack_rgba(a), unpack_rgba(b));
+            (0..4).map(|c| (a[c] - b[c]).pow(2) as u32).sum::<u32>()
+        }).sum()
+    }
+
+    /// blocks with smooth gradients, hard edges, alpha and noise
+    fn test_blocks() -> Vec<[u32; 16]> {
+        let mut seed = 12345u32;
+        let mut random = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed
+        };
+        let mut blocks = vec![
+            [0xFF336699; 16],
+            [0x80FFFFFF; 16],
+            std::array::from_fn(|i| 0xFF000000 | (i as u32 * 16) << 16 | (255 - i as u32 * 16) << 8 | 0x40),
+            std::array::from_fn(|i| ((i as u32 * 17) << 24) | 0x00C08040),
+            std::array::from_fn(|i| if i % 4 < 2 { 0xFFFF0000 } else { 0xFF0000FF }),
+            std::array::from_fn(|i| if i < 8 { 0x20FF8000 } else { 0xE00080FF }),
+        ];
+        for _ in 0..6 {
+            blocks.push(std::array::from_fn(|_| random()));
+        }
+        blocks
+    }
+
+    #[test]
+    fn every_mode_decodes_as_estimated() {
+        for mode in 0..8 {
+            for rotations in [false, true] {
+                let options = BC7Options { modes: 1 << mode, partitions: 64, refine_passes: 1, rotations, threads: 1 };
+                for block in test_blocks() {
+                    let (data, error) = encode_block_with_error(&block, &options);
+                    // mode bits
+                    assert_eq!(data[0].trailing_zeros() as usize, mode);
+                    let decoded = decode(&data, 4, 4);
+                    assert_eq!(squared_error(&decoded, &block), error, "mode {}", mode);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn solid_and_two_color_blocks() {
+        let options = BC7Options::new(BC7Quality::Normal);
+        let block = [0xFF336699; 16];
+        let decoded = decode(&encode_bc7_block(&block, &options), 4, 4);
+        assert!(squared_error(&decoded, &block) <= 16);
+
+        let block: [u32; 16] = std::array::from_fn(|i| if i % 4 < 2 { 0xFFFF0000 } else { 0xFF0000FF });
+        let decoded = decode(&encode_bc7_block(&block, &options), 4, 4);
+        assert_eq!(decoded, block);
+    }
+
+    #[test]
+    fn higher_quality_is_better() {
+        let mut total = [0u64; 3];
+        for block in test_blocks() {
+            for (i, quality) in [BC7Quality::Fast, BC7Quality::Normal, BC7Quality::High].into_iter().enumerate() {
+                total[i] += encode_block_with_error(&block, &BC7Options::new(quality)).1 as u64;
+            }
+        }
+        assert!(total[1] <= total[0], "{:?}", total);
+        assert!(total[2] <= total[1], "{:?}", total);
+    }
+
+    #[test]
+    fn smpte_bar() {
+        let mut video = GVVideo::load(Cursor::new(TEST_GV)).unwrap();
+        let frame = video.read_frame(0).unwrap();
+        let data = encode_bc7(&frame, 640, 360, &BC7Options::new(BC7Quality::Fast)).unwrap();
+        assert_eq!(data.len(), GVFormat::BC7.frame_bytes(640, 360));
+        let mse = squared_error(&decode(&data, 640, 360), &frame) as f64 / (640 * 360 * 4) as f64;
+        assert!(mse < 1.0, "mse: {}", mse);
+    }
+
+    #[test]
+    fn threads_produce_same_data() {
+        let mut video = GVVideo::load(Cursor::new(TEST_ALPHA_GV)).unwrap();
+        let frame = video.read_frame(0).unwrap();
+        // crop to keep the test fast
+        let (w, h) = (70, 45);
+        let cropped: Vec<u32> = (0..w * h).map(|i| frame[(i % w) * 9 + (i / w) * 8 * 640]).collect();
+
+        let single = BC7Options { threads: 1, ..BC7Options::new(BC7Quality::Normal) };
+        let multi = BC7Options { threads: 4, ..single };
+        let data = encode_bc7(&cropped, w, h, &single).unwrap();
+        assert_eq!(data, encode_bc7(&cropped, w, h, &multi).unwrap());
+        assert_eq!(data.len(), GVFormat::BC7.frame_bytes(w as u32, h as u32));
+
+        let mse = squared_error(&decode(&data, w, h), &cropped) as f64 / (w * h * 4) as f64;
+        assert!(mse < 8.0, "mse: {}", mse);
+    }
+}
@@ -0,0 +1,393 @@
+// gv: command-line tool for GV video
+//
+// gv info <file.gv>
+// gv verify <file.gv>
+// gv repair <file.gv> <out.gv>
+// gv extract <file.gv> <out_dir> [--start N] [--end N]
+// gv encode <out.gv> <image.png | dir>... [--fps F] [--format dxt1|dxt3|dxt5|bc7] [--quality fast|normal|high] [--dither]
+
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs::File,
+    io::{BufReader, BufWriter, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use gv_video::{
+    encode_bc1, encode_bc2, encode_bc3, encode_bc7, get_rgba_vec_from_frame, BC7Options, BC7Quality, BCQuality,
+    GVFormat, GVVideo, GVWriter,
+};
+
+const USAGE: &str = "\
+usage:
+  gv info <file.gv>
+  gv verify <file.gv>
+  gv repair <file.gv> <out.gv>
+  gv extract <file.gv> <out_dir> [--start N] [--end N]
+  gv encode <out.gv> <image.png | dir>... [--fps F] [--format dxt1|dxt3|dxt5|bc7] [--quality fast|normal|high] [--dither]";
+
+type CliResult<T> = Result<T, Box<dyn Error>>;
+
+/// options without a value
+const FLAGS: &[&str] = &["dither"];
+
+/// positional arguments, `--key value` options and `--flag`s
+struct Args {
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+    flags: Vec<String>,
+}
+
+impl Args {
+    fn parse(args: impl Iterator<Item = String>) -> CliResult<Args> {
+        let mut positional = Vec::new();
+        let mut options = HashMap::new();
+        let mut flags = Vec::new();
+        let mut args = args.peekable();
+        while let Some(arg) = args.next() {
+            if let Some(key) = arg.strip_prefix("--") {
+                if FLAGS.contains(&key) {
+                    flags.push(key.to_string());
+                    continue;
+                }
+                let value = args.next().ok_or_else(|| format!("missing value for --{}", key))?;
+                options.insert(key.to_string(), value);
+            } else {
+                positional.push(arg);
+            }
+        }
+        Ok(Args { positional, options, flags })
+    }
+
+    fn flag(&self, key: &str) -> bool {
+        self.flags.iter().any(|flag| flag == key)
+    }
+
+    fn option<T: std::str::FromStr>(&self, key: &str, default: T) -> CliResult<T> {
+        match self.options.get(key) {
+            Some(value) => value.parse().map_err(|_| format!("invalid value for --{}: {}", key, value).into()),
+            None => Ok(default),
+        }
+    }
+}
+
+fn info(args: &Args) -> CliResult<()> {
+    let [path] = args.positional.as_slice() else {
+        return Err(USAGE.into());
+    };
+    let video = GVVideo::load_from_file(path)?;
+    let header = &video.header;
+    let file_size = std::fs::metadata(path)?.len();
+
+    println!("file: {}", path);
+    println!("file size: {}", file_size);
+    println!("width: {}", header.width);
+    println!("height: {}", header.height);
+    println!("frame count: {}", header.frame_count);
+    let frame_rate = video.get_frame_rate();
+    if frame_rate.denominator() == 1 {
+        println!("fps: {}", header.fps);
+    } else {
+        println!("fps: {} ({})", header.fps, frame_rate);
+    }
+    println!("format: {:?}", header.format);
+    println!("frame bytes: {}", header.frame_bytes);
+    println!("duration: {:.3}s", video.get_duration().as_secs_f64());
+    if header.frame_count > 0 {
+        println!("timecode: {} - {}", video.timecode_of(0, true), video.timecode_of(header.frame_count - 1, true));
+    }
+
+    let blocks = &video.address_size_blocks;
+    if let (Some(min), Some(max)) = (blocks.iter().map(|b| b.size).min(), blocks.iter().map(|b| b.size).max()) {
+        let total: u64 = blocks.iter().map(|b| b.size).sum();
+        println!("lz4 size: min {}, max {}, average {:.1}, total {}", min, max, total as f64 / blocks.len() as f64, total);
+    }
+    println!("frames:");
+    for (i, block) in blocks.iter().enumerate() {
+        println!("  {}: address {}, size {}", i, block.address, block.size);
+    }
+    Ok(())
+}
+
+fn verify(args: &Args) -> CliResult<()> {
+    let [path] = args.positional.as_slice() else {
+        return Err(USAGE.into());
+    };
+    let mut video = GVVideo::load_from_file(path)?;
+    let report = video.validate()?;
+    for issue in &report.issues {
+        println!("{}", issue);
+    }
+    if !report.is_ok() {
+        return Err(format!("{}: {} issues found", path, report.issues.len()).into());
+    }
+    println!("{}: ok ({} frames)", path, video.get_frame_count());
+    Ok(())
+}
+
+/// rebuild address table of a file whose writer did not finish, then write a valid file
+fn repair(args: &Args) -> CliResult<()> {
+    let [path, out_path] = args.positional.as_slice() else {
+        return Err(USAGE.into());
+    };
+    // creating the output would truncate the input before its frames are copied
+    if Path::new(out_path).exists() && std::fs::canonicalize(path)? == std::fs::canonicalize(out_path)? {
+        return Err(format!("{}: output must differ from the input", out_path).into());
+    }
+    let mut video = GVVideo::recover(BufReader::new(File::open(path)?))?;
+    let header = video.header.clone();
+    let mut writer = GVWriter::new(BufWriter::new(File::create(out_path)?), header.width, header.height, header.fps, header.format)?;
+    // recovered blocks decompress to frame bytes, so they are copied without compressing again
+    let mut lz4 = Vec::new();
+    for block in &video.address_size_blocks {
+        lz4.resize(block.size as usize, 0);
+        video.reader.seek(SeekFrom::Start(block.address))?;
+        video.reader.read_exact(&mut lz4)?;
+        writer.write_frame_lz4(&lz4)?;
+    }
+    writer.finish()?;
+    println!("wrote {} ({} frames recovered)", out_path, header.frame_count);
+    Ok(())
+}
+
+fn extract(args: &Args) -> CliResult<()> {
+    let [path, out_dir] = args.positional.as_slice() else {
+        return Err(USAGE.into());
+    };
+    let mut video = GVVideo::load_from_file(path)?;
+    let (width, height) = video.get_resolution();
+    let start = args.option("start", 0)?;
+    let end = args.option("end", video.get_frame_count())?.min(video.get_frame_count());
+
+    std::fs::create_dir_all(out_dir)?;
+    for frame_id in start..end {
+        let frame = video.read_frame(frame_id)?;
+        let out_path = Path::new(out_dir).join(format!("{:05}.png", frame_id));
+        let mut encoder = png::Encoder::new(BufWriter::new(File::create(&out_path)?), width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&get_rgba_vec_from_frame(&frame))?;
+        println!("{}", out_path.display());
+    }
+    Ok(())
+}
+
+/// read png into BGRA u32 frame
+fn read_png(path: &Path) -> CliResult<(u32, u32, Vec<u32>)> {
+    let mut decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf)?;
+    let buf = &buf[..info.buffer_size()];
+
+    let pixel = |r: u8, g: u8, b: u8, a: u8| u32::from_le_bytes([b, g, r, a]);
+    let frame = match info.color_type {
+        png::ColorType::Rgba => buf.chunks_exact(4).map(|p| pixel(p[0], p[1], p[2], p[3])).collect(),
+        png::ColorType::Rgb => buf.chunks_exact(3).map(|p| pixel(p[0], p[1], p[2], 255)).collect(),
+        png::ColorType::GrayscaleAlpha => buf.chunks_exact(2).map(|p| pixel(p[0], p[0], p[0], p[1])).collect(),
+        png::ColorType::Grayscale => buf.iter().map(|&v| pixel(v, v, v, 255)).collect(),
+        png::ColorType::Indexed => return Err(format!("{}: unsupported color type", path.display()).into()),
+    };
+    Ok((info.width, info.height, frame))
+}
+
+/// input files in order, directories are expanded to their png files sorted by name
+fn collect_inputs(inputs: &[String]) -> CliResult<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for input in inputs {
+        let path = PathBuf::from(input);
+        if path.is_dir() {
+            let mut entries: Vec<PathBuf> = std::fs::read_dir(&path)?
+                .map(|entry| entry.map(|e| e.path()))
+                .collect::<Result<_, _>>()?;
+            entries.retain(|p| p.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("png")));
+            entries.sort();
+            paths.extend(entries);
+        } else {
+            paths.push(path);
+        }
+    }
+    Ok(paths)
+}
+
+fn encode(args: &Args) -> CliResult<()> {
+    let [out_path, inputs @ ..] = args.positional.as_slice() else {
+        return Err(USAGE.into());
+    };
+    let inputs = collect_inputs(inputs)?;
+    if inputs.is_empty() {
+        return Err("no input images".into());
+    }
+
+    let fps = args.option("fps", 30.0f32)?;
+    let format = match args.option("format", "dxt1".to_string())?.to_ascii_lowercase().as_str() {
+        "dxt1" | "bc1" => GVFormat::DXT1,
+        "dxt3" | "bc2" => GVFormat::DXT3,
+        "dxt5" | "bc3" => GVFormat::DXT5,
+        "bc7" => GVFormat::BC7,
+        other => return Err(format!("unknown format: {}", other).into()),
+    };
+    let (quality, bc7_quality) = match args.option("quality", "normal".to_string())?.to_ascii_lowercase().as_str() {
+        "fast" => (BCQuality::Fast, BC7Quality::Fast),
+        "normal" => (BCQuality::High, BC7Quality::Normal),
+        "high" => (BCQuality::High, BC7Quality::High),
+        other => return Err(format!("unknown quality: {}", other).into()),
+    };
+    let bc7_options = BC7Options::new(bc7_quality);
+    let dither = args.flag("dither");
+
+    // the first image gives the size, and is encoded without decoding it again
+    let (width, height, first) = read_png(&inputs[0])?;
+    let mut first = Some(first);
+    let mut writer = GVWriter::new(BufWriter::new(File::create(out_path)?), width, height, fps, format)?;
+    for input in &inputs {
+        let (w, h, frame) = match first.take() {
+            Some(frame) => (width, height, frame),
+            None => read_png(input)?,
+        };
+        if (w, h) != (width, height) {
+            return Err(format!("{}: size {}x{} differs from {}x{}", input.display(), w, h, width, height).into());
+        }
+        let (w, h) = (w as usize, h as usize);
+        let data = match format {
+            GVFormat::DXT1 => encode_bc1(&frame, w, h, quality)?,
+            GVFormat::DXT3 => encode_bc2(&frame, w, h, quality, dither)?,
+            GVFormat::DXT5 => encode_bc3(&frame, w, h, quality)?,
+            GVFormat::BC7 => encode_bc7(&frame, w, h, &bc7_options)?,
+        };
+        writer.write_frame(&data)?;
+        println!("{}", input.display());
+    }
+    let frame_count = writer.get_frame_count();
+    writer.finish()?;
+    println!("wrote {} ({} frames, {}x{}, {:?})", out_path, frame_count, width, height, format);
+    Ok(())
+}
+
+/// run subcommand, `args` excludes the program name
+fn run(mut args: impl Iterator<Item = String>) -> CliResult<()> {
+    let command = args.next();
+    let args = Args::parse(args)?;
+    match command.as_deref() {
+        Some("info") => info(&args),
+        Some("verify") => verify(&args),
+        Some("repair") => repair(&args),
+        Some("extract") => extract(&args),
+        Some("encode") => encode(&args),
+        _ => Err(USAGE.into()),
+    }
+}
+
+fn main() -> ExitCode {
+    match run(std::env::args().skip(1)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gv(args: &[&str]) -> CliResult<()> {
+        run(args.iter().map(|arg| arg.to_string()))
+    }
+
+    /// temporary directory removed on drop
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> TempDir {
+            let path = std::env::temp_dir().join(format!("gv-cli-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&path);
+            std::fs::create_dir_all(&path).unwrap();
+            TempDir(path)
+        }
+
+        fn path(&self, name: &str) -> String {
+            self.0.join(name).to_str().unwrap().to_string()
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn write_png(path: &str, width: u32, height: u32, shade: u8) {
+        let mut encoder = png::Encoder::new(BufWriter::new(File::create(path).unwrap()), width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let data: Vec<u8> = (0..width * height).flat_map(|i| [shade, (i % 256) as u8, 128, 255]).collect();
+        encoder.write_header().unwrap().write_image_data(&data).unwrap();
+    }
+
+    #[test]
+    fn encode_then_inspect() {
+        let dir = TempDir::new("encode");
+        std::fs::create_dir_all(dir.path("frames")).unwrap();
+        for i in 0..3 {
+            write_png(&dir.path(&format!("frames/{:02}.png", i)), 10, 6, i * 80);
+        }
+        let out = dir.path("out.gv");
+        gv(&["encode", &out, &dir.path("frames"), "--fps", "24", "--format", "dxt5", "--quality", "fast"]).unwrap();
+
+        let video = GVVideo::load_from_file(&out).unwrap();
+        assert_eq!(video.get_resolution(), (10, 6));
+        assert_eq!(video.get_frame_count(), 3);
+        assert_eq!(video.get_format(), GVFormat::DXT5);
+
+        gv(&["info", &out]).unwrap();
+        gv(&["verify", &out]).unwrap();
+        gv(&["extract", &out, &dir.path("extracted"), "--start", "1"]).unwrap();
+        assert!(Path::new(&dir.path("extracted/00002.png")).exists());
+        assert!(!Path::new(&dir.path("extracted/00000.png")).exists());
+
+        // address table cut off, as left by a crashed recorder
+        let data = std::fs::read(&out).unwrap();
+        let broken = dir.path("broken.gv");
+        std::fs::write(&broken, &data[..data.len() - 3 * 16]).unwrap();
+        assert!(gv(&["verify", &broken]).is_err());
+        let fixed = dir.path("fixed.gv");
+        gv(&["repair", &broken, &fixed]).unwrap();
+        gv(&["verify", &fixed]).unwrap();
+        assert_eq!(std::fs::read(&fixed).unwrap(), data);
+
+        // repairing in place would destroy the input
+        assert!(gv(&["repair", &broken, &broken]).is_err());
+        assert_eq!(std::fs::read(&broken).unwrap(), data[..data.len() - 3 * 16]);
+    }
+
+    #[test]
+    fn dither_option() {
+        let dir = TempDir::new("dither");
+        let frame = dir.path("frame.png");
+        write_png(&frame, 8, 8, 100);
+        let (plain, dithered) = (dir.path("plain.gv"), dir.path("dithered.gv"));
+        gv(&["encode", &plain, &frame, "--format", "dxt3"]).unwrap();
+        gv(&["encode", &dithered, &frame, "--format", "dxt3", "--dither"]).unwrap();
+
+        // default is the library default, without dithering
+        let (w, h, data) = read_png(Path::new(&frame)).unwrap();
+        let (w, h) = (w as usize, h as usize);
+        let expected = encode_bc2(&data, w, h, BCQuality::High, false).unwrap();
+        assert_eq!(GVVideo::load_from_file(&plain).unwrap().read_frame_compressed(0).unwrap(), expected);
+        let expected = encode_bc2(&data, w, h, BCQuality::High, true).unwrap();
+        assert_eq!(GVVideo::load_from_file(&dithered).unwrap().read_frame_compressed(0).unwrap(), expected);
+    }
+
+    #[test]
+    fn usage_errors() {
+        assert!(gv(&[]).is_err());
+        assert!(gv(&["unknown"]).is_err());
+        assert!(gv(&["info"]).is_err());
+        assert!(gv(&["encode", "out.gv", "--fps"]).is_err());
+    }
+}
@@ -0,0 +1,211 @@
+// LRU cache of frames around GVVideo, bounded by a byte budget
+//
+// for scrubbing UIs which read the same frames repeatedly, so LZ4 and BC decoding runs once per frame.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{Read, Seek},
+};
+
+use crate::{GVError, GVVideo};
+
+/// hit / miss statistics of `GVFrameCache`
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct GVCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    /// frames currently cached
+    pub frames: usize,
+    /// bytes currently cached
+    pub bytes: usize,
+}
+
+struct Entry<T> {
+    data: T,
+    bytes: usize,
+    /// key in `GVFrameCache::lru`
+    last_used: u64,
+}
+
+/// frame cache keyed by frame id, see `GVFrameCache::decoded` and `GVFrameCache::compressed`
+///
+/// least recently used frames are evicted when cached frames exceed `budget` bytes.
+/// the most recent frame is always kept, even if it alone exceeds the budget.
+pub struct GVFrameCache<Reader: Read + Seek, T> {
+    video: GVVideo<Reader>,
+    read: fn(&mut GVVideo<Reader>, u32) -> Result<T, GVError>,
+    size: fn(&T) -> usize,
+    budget: usize,
+    entries: HashMap<u32, Entry<T>>,
+    /// last use -> frame id, oldest first
+    lru: BTreeMap<u64, u32>,
+    tick: u64,
+    stats: GVCacheStats,
+}
+
+impl<Reader: Read + Seek> GVFrameCache<Reader, Vec<u32>> {
+    /// cache decompressed frames (BGRA u32, same as `read_frame`)
+    pub fn decoded(video: GVVideo<Reader>, budget: usize) -> Self {
+        GVFrameCache::new(video, budget, GVVideo::read_frame, |frame| frame.len() * 4)
+    }
+}
+
+impl<Reader: Read + Seek> GVFrameCache<Reader, Vec<u8>> {
+    /// cache BC compressed frames (same as `read_frame_compressed`)
+    pub fn compressed(video: GVVideo<Reader>, budget: usize) -> Self {
+        GVFrameCache::new(video, budget, GVVideo::read_frame_compressed, Vec::len)
+    }
+}
+
+impl<Reader: Read + Seek, T> GVFrameCache<Reader, T> {
+    fn new(video: GVVideo<Reader>, budget: usize, read: fn(&mut GVVideo<Reader>, u32) -> Result<T, GVError>, size: fn(&T) -> usize) -> Self {
+        GVFrameCache {
+            video,
+            read,
+            size,
+            budget,
+            entries: HashMap::new(),
+            lru: BTreeMap::new(),
+            tick: 0,
+            stats: GVCacheStats::default(),
+        }
+    }
+
+    /// cached frame, read from the video on miss (errors are not cached)
+    pub fn get(&mut self, frame_id: u32) -> Result<&T, GVError> {
+        self.tick += 1;
+        if let Some(entry) = self.entries.get_mut(&frame_id) {
+            self.stats.hits += 1;
+            self.lru.remove(&entry.last_used);
+            self.lru.insert(self.tick, frame_id);
+            entry.last_used = self.tick;
+        } else {
+            self.stats.misses += 1;
+            let data = (self.read)(&mut self.video, frame_id)?;
+            let bytes = (self.size)(&data);
+            self.stats.bytes += bytes;
+            self.lru.insert(self.tick, frame_id);
+            self.entries.insert(frame_id, Entry { data, bytes, last_used: self.tick });
+            self.evict();
+        }
+        self.stats.frames = self.entries.len();
+        Ok(&self.entries[&frame_id].data)
+    }
+
+    /// evict least recently used frames until within budget, keeping the most recent one
+    fn evict(&mut self) {
+        while self.stats.bytes > self.budget && self.lru.len() > 1 {
+            let (_, frame_id) = self.lru.pop_first().unwrap();
+            let entry = self.entries.remove(&frame_id).unwrap();
+            self.stats.bytes -= entry.bytes;
+            self.stats.evictions += 1;
+        }
+        self.stats.frames = self.entries.len();
+    }
+
+    pub fn contains(&self, frame_id: u32) -> bool {
+        self.entries.contains_key(&frame_id)
+    }
+
+    pub fn stats(&self) -> GVCacheStats {
+        self.stats
+    }
+
+    /// reset hits, misses and evictions (cached frames are kept)
+    pub fn reset_stats(&mut self) {
+        self.stats = GVCacheStats { frames: self.stats.frames, bytes: self.stats.bytes, ..Default::default() };
+    }
+
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// change budget, evicting frames if needed
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        self.evict();
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.lru.clear();
+        self.stats.frames = 0;
+        self.stats.bytes = 0;
+    }
+
+    pub fn video(&self) -> &GVVideo<Reader> {
+        &self.video
+    }
+
+    /// underlying video, reading from it directly bypasses the cache
+    pub fn video_mut(&mut self) -> &mut GVVideo<Reader> {
+        &mut self.video
+    }
+
+    pub fn into_inner(self) -> GVVideo<Reader> {
+        self.video
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    // 10px 5sec 1fps
+    const TEST_10PX_GV: &[u8; 474] = include_bytes!("../test_asset/test-10px.gv");
+
+    fn load() -> GVVideo<Cursor<&'static [u8]>> {
+        GVVideo::load(Cursor::new(&TEST_10PX_GV[..])).unwrap()
+    }
+
+    #[test]
+    fn hits_and_misses() {
+        let mut video = load();
+        let expected: Vec<Vec<u32>> = (0..5).map(|i| video.read_frame(i).unwrap()).collect();
+
+        let mut cache = GVFrameCache::decoded(video, usize::MAX);
+        for id in [0, 1, 0, 0, 2, 1] {
+            assert_eq!(cache.get(id).unwrap(), &expected[id as usize]);
+        }
+        let stats = cache.stats();
+        assert_eq!(stats, GVCacheStats { hits: 3, misses: 3, evictions: 0, frames: 3, bytes: 3 * 10 * 10 * 4 });
+
+        assert!(matches!(cache.get(5), Err(GVError::FrameOutOfRange { id: 5, count: 5 })));
+        assert_eq!(cache.stats().frames, 3);
+
+        cache.reset_stats();
+        assert_eq!(cache.stats(), GVCacheStats { frames: 3, bytes: 1200, ..Default::default() });
+        cache.clear();
+        assert_eq!(cache.stats(), GVCacheStats::default());
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let mut video = load();
+        let frame_bytes = video.read_frame_compressed(0).unwrap().len();
+
+        let mut cache = GVFrameCache::compressed(video, frame_bytes * 2);
+        cache.get(0).unwrap();
+        cache.get(1).unwrap();
+        cache.get(0).unwrap();
+        cache.get(2).unwrap();
+        assert!(cache.contains(0));
+        assert!(!cache.contains(1));
+        assert!(cache.contains(2));
+        assert_eq!(cache.stats().evictions, 1);
+        assert_eq!(cache.stats().bytes, frame_bytes * 2);
+
+        // most recent frame is kept, even over budget
+        cache.set_budget(1);
+        assert!(cache.contains(2));
+        assert_eq!(cache.stats().frames, 1);
+        cache.get(3).unwrap();
+        assert!(!cache.contains(2));
+        assert!(cache.contains(3));
+
+        let mut video = cache.into_inner();
+        assert_eq!(video.read_frame_compressed(0).unwrap().len(), frame_bytes);
+    }
+}
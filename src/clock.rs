@@ -0,0 +1,489 @@
+// clocks driving playback, and frame selection following a clock
+//
+// a clock is anything which tells the current time as an `Instant`: the system clock, a manual clock for tests,
+// or an external master (audio device, timecode) fed with its position. `GVFrameScheduler` drives a `GVPlayer`
+// from a clock and reports dropped and repeated frames as events.
+
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use crate::{GVPlayer, LoopMode};
+
+/// signed seconds from `from` to `to`
+pub(crate) fn seconds_between(from: Instant, to: Instant) -> f64 {
+    match to.checked_duration_since(from) {
+        Some(elapsed) => elapsed.as_secs_f64(),
+        None => -(from - to).as_secs_f64(),
+    }
+}
+
+pub trait GVClock {
+    fn now(&self) -> Instant;
+}
+
+/// `Instant::now()`
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl GVClock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// clock which only moves when told to (tests, offline rendering)
+#[derive(Debug)]
+pub struct ManualClock {
+    origin: Instant,
+    /// nanoseconds since origin
+    elapsed: AtomicU64,
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        ManualClock::new()
+    }
+}
+
+impl ManualClock {
+    pub fn new() -> ManualClock {
+        ManualClock { origin: Instant::now(), elapsed: AtomicU64::new(0) }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_nanos(self.elapsed.load(Ordering::Relaxed))
+    }
+
+    pub fn set(&self, elapsed: Duration) {
+        self.elapsed.store(elapsed.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.elapsed.fetch_add(duration.as_nanos() as u64, Ordering::Relaxed);
+    }
+}
+
+impl GVClock for ManualClock {
+    fn now(&self) -> Instant {
+        self.origin + self.elapsed()
+    }
+}
+
+/// clock following an external master (e.g. audio device position), fed with `update`
+///
+/// between updates the position is extrapolated from the system clock. small drift is corrected gradually by
+/// running slightly faster or slower for `correction_time`, drift over `max_drift` makes the clock jump. an update
+/// with the same position as the previous one means the master is paused, the clock holds until it moves again.
+#[derive(Debug, Clone)]
+pub struct ExternalClock {
+    origin: Instant,
+    /// corrected position in seconds at `updated_at`
+    position: f64,
+    updated_at: Instant,
+    /// seconds of position per second of system clock during the correction, then 1.0
+    rate: f64,
+    /// seconds after `updated_at` running at `rate`
+    correction: f64,
+    /// last position reported by the master, None until the first update
+    reported: Option<f64>,
+    paused: bool,
+    max_drift: Duration,
+    correction_time: Duration,
+}
+
+impl Default for ExternalClock {
+    fn default() -> Self {
+        ExternalClock::new()
+    }
+}
+
+impl ExternalClock {
+    /// jumps on drift over 100ms, smaller drift is corrected within 1s
+    pub fn new() -> ExternalClock {
+        ExternalClock::with_correction(Duration::from_millis(100), Duration::from_secs(1))
+    }
+
+    pub fn with_correction(max_drift: Duration, correction_time: Duration) -> ExternalClock {
+        let now = Instant::now();
+        ExternalClock {
+            origin: now,
+            position: 0.0,
+            updated_at: now,
+            rate: 1.0,
+            correction: 0.0,
+            reported: None,
+            paused: false,
+            max_drift,
+            correction_time,
+        }
+    }
+
+    /// report position of the master clock now
+    pub fn update(&mut self, position: Duration) -> Option<GVClockEvent> {
+        self.update_at(position, Instant::now())
+    }
+
+    /// report position of the master clock at system instant `at`
+    pub fn update_at(&mut self, position: Duration, at: Instant) -> Option<GVClockEvent> {
+        let reported = position.as_secs_f64();
+        let predicted = self.extrapolate(at);
+        let drift = reported - predicted;
+        let previous = self.reported.replace(reported);
+        self.updated_at = at;
+        self.rate = 1.0;
+        self.correction = 0.0;
+
+        // first update, master paused, or master resumed: sync without event
+        let paused = previous == Some(reported);
+        if previous.is_none() || paused || self.paused {
+            self.position = reported;
+            self.paused = paused;
+            return None;
+        }
+        if drift.abs() > self.max_drift.as_secs_f64() {
+            self.position = reported;
+            return Some(GVClockEvent::Resynced { drift });
+        }
+
+        // keep position continuous, remove drift over correction time
+        self.position = predicted;
+        let correction_time = self.correction_time.as_secs_f64();
+        if correction_time > 0.0 {
+            self.rate = (1.0 + drift / correction_time).clamp(0.5, 1.5);
+            self.correction = correction_time;
+        }
+        (drift != 0.0).then_some(GVClockEvent::DriftCorrected { drift })
+    }
+
+    /// corrected position in seconds at system instant `at`
+    fn extrapolate(&self, at: Instant) -> f64 {
+        if self.reported.is_none() || self.paused {
+            return self.position;
+        }
+        let elapsed = seconds_between(self.updated_at, at);
+        let corrected = elapsed.min(self.correction);
+        self.position + corrected * self.rate + (elapsed - corrected)
+    }
+
+    /// corrected position at system instant `at`
+    pub fn position_at(&self, at: Instant) -> Duration {
+        Duration::try_from_secs_f64(self.extrapolate(at)).unwrap_or_default()
+    }
+}
+
+impl GVClock for ExternalClock {
+    fn now(&self) -> Instant {
+        self.origin + self.position_at(Instant::now())
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum GVClockEvent {
+    /// `count` frames were skipped to catch up with the clock, `frame_id` is shown
+    FrameDropped { frame_id: u32, count: u32 },
+    /// clock moved backward (e.g. an `ExternalClock` resync), the earlier `frame_id` is shown again
+    FrameRepeated { frame_id: u32 },
+    /// external clock drifted by `drift` seconds (positive: master ahead), corrected gradually
+    DriftCorrected { drift: f64 },
+    /// external clock drifted by `drift` seconds, over the limit, so the clock jumped
+    Resynced { drift: f64 },
+}
+
+/// what `GVFrameScheduler` does when the clock is more than one frame ahead
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum DropPolicy {
+    /// skip to the frame of the clock
+    #[default]
+    Drop,
+    /// show every frame, falling behind the clock by up to `max_lag` frames before skipping
+    Hold { max_lag: u32 },
+}
+
+/// events kept until `take_events`, older ones are discarded
+const MAX_EVENTS: usize = 64;
+
+/// `GVPlayer` driven by a clock, call `tick()` once per display refresh
+///
+/// with an `ExternalClock`, feed the master position through `update_clock` so drift events are reported together
+/// with dropped and repeated frames.
+///
+/// showing a frame on several ticks because the display refreshes faster than the video (30fps on 60Hz) is not
+/// an event.
+pub struct GVFrameScheduler<Clock: GVClock> {
+    clock: Clock,
+    player: GVPlayer,
+    policy: DropPolicy,
+    /// unwrapped frame shown by the last tick
+    last: Option<i64>,
+    events: Vec<GVClockEvent>,
+}
+
+impl<Clock: GVClock> GVFrameScheduler<Clock> {
+    /// paused at frame 0, see `GVPlayer::new`
+    pub fn new(clock: Clock, frame_count: u32, fps: f32, policy: DropPolicy) -> Self {
+        let player = GVPlayer::new(frame_count, fps, clock.now());
+        GVFrameScheduler { clock, player, policy, last: None, events: Vec::new() }
+    }
+
+    /// frame id to show now
+    pub fn tick(&mut self) -> u32 {
+        let now = self.clock.now();
+        let target = self.player.raw_frame(now);
+        let shown = match self.last {
+            Some(last) if self.player.is_playing() && !self.player.is_finished(now) => {
+                let step = if self.player.rate() < 0.0 { -1 } else { 1 };
+                // frames the clock is ahead of the last shown frame, in playback direction
+                let ahead = (target - last) * step;
+                match ahead {
+                    ..=-1 => {
+                        self.push_event(GVClockEvent::FrameRepeated { frame_id: self.player.wrap(target) });
+                        target
+                    }
+                    2.. => match self.policy {
+                        DropPolicy::Hold { max_lag } if ahead - 1 <= max_lag as i64 => last + step,
+                        _ => {
+                            let count = (ahead - 1).min(u32::MAX as i64) as u32;
+                            self.push_event(GVClockEvent::FrameDropped { frame_id: self.player.wrap(target), count });
+                            target
+                        }
+                    },
+                    // same frame until the clock reaches the next one, or the next frame
+                    _ => target,
+                }
+            }
+            _ => target,
+        };
+        self.last = Some(shown);
+        self.player.wrap(shown)
+    }
+
+    /// consecutive drops are merged into one event, at most `MAX_EVENTS` are kept
+    fn push_event(&mut self, event: GVClockEvent) {
+        match (self.events.last_mut(), event) {
+            (Some(GVClockEvent::FrameDropped { frame_id, count }), GVClockEvent::FrameDropped { frame_id: id, count: more }) => {
+                *frame_id = id;
+                *count = count.saturating_add(more);
+            }
+            _ => {
+                if self.events.len() == MAX_EVENTS {
+                    self.events.remove(0);
+                }
+                self.events.push(event);
+            }
+        }
+    }
+
+    /// events since the last call, the most recent 64 when more happened
+    pub fn take_events(&mut self) -> Vec<GVClockEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    pub fn play(&mut self) {
+        self.player.play(self.clock.now());
+        self.last = None;
+    }
+
+    pub fn pause(&mut self) {
+        self.player.pause(self.clock.now());
+    }
+
+    pub fn seek(&mut self, frame_id: u32) {
+        self.player.seek(frame_id, self.clock.now());
+        self.last = None;
+    }
+
+    pub fn set_rate(&mut self, rate: f64) {
+        self.player.set_rate(rate, self.clock.now());
+        self.last = None;
+    }
+
+    pub fn set_loop_mode(&mut self, loop_mode: LoopMode) {
+        self.player.set_loop_mode(loop_mode, self.clock.now());
+        self.last = None;
+    }
+
+    pub fn set_policy(&mut self, policy: DropPolicy) {
+        self.policy = policy;
+    }
+
+    pub fn player(&self) -> &GVPlayer {
+        &self.player
+    }
+
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    /// `update_clock` reports drift events of an `ExternalClock` along with frame events
+    pub fn clock_mut(&mut self) -> &mut Clock {
+        &mut self.clock
+    }
+}
+
+impl GVFrameScheduler<ExternalClock> {
+    /// report position of the master clock now, drift correction is reported by `take_events`
+    pub fn update_clock(&mut self, position: Duration) {
+        self.update_clock_at(position, Instant::now());
+    }
+
+    /// report position of the master clock at system instant `at`, drift correction is reported by `take_events`
+    pub fn update_clock_at(&mut self, position: Duration, at: Instant) {
+        if let Some(event) = self.clock.update_at(position, at) {
+            self.push_event(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn manual_clock() {
+        let clock = ManualClock::new();
+        let start = clock.now();
+        clock.advance(ms(1500));
+        assert_eq!(clock.now() - start, ms(1500));
+        clock.set(ms(200));
+        assert_eq!(clock.now() - start, ms(200));
+        assert!(SystemClock.now() >= SystemClock.now() - ms(1));
+    }
+
+    #[test]
+    fn drop_and_repeat() {
+        let mut scheduler = GVFrameScheduler::new(ManualClock::new(), 100, 10.0, DropPolicy::Drop);
+        scheduler.play();
+        let mut frames = Vec::new();
+        for step in [0, 100, 100, 50, 50, 300, 100] {
+            scheduler.clock().advance(ms(step));
+            frames.push(scheduler.tick());
+        }
+        // showing 2 twice at display rate is not an event
+        assert_eq!(frames, vec![0, 1, 2, 2, 3, 6, 7]);
+        assert_eq!(scheduler.take_events(), vec![GVClockEvent::FrameDropped { frame_id: 6, count: 2 }]);
+
+        // clock moving backward shows an earlier frame again
+        scheduler.clock().set(ms(650));
+        assert_eq!(scheduler.tick(), 6);
+        assert_eq!(scheduler.take_events(), vec![GVClockEvent::FrameRepeated { frame_id: 6 }]);
+        assert!(scheduler.take_events().is_empty());
+
+        // no events while paused or after seeking
+        scheduler.pause();
+        scheduler.tick();
+        scheduler.seek(50);
+        assert_eq!(scheduler.tick(), 50);
+        assert!(scheduler.take_events().is_empty());
+
+        // backward
+        scheduler.set_rate(-1.0);
+        scheduler.play();
+        scheduler.tick();
+        scheduler.clock().advance(ms(400));
+        assert_eq!(scheduler.tick(), 46);
+        assert_eq!(scheduler.take_events(), vec![GVClockEvent::FrameDropped { frame_id: 46, count: 3 }]);
+    }
+
+    #[test]
+    fn bounded_events() {
+        let mut scheduler = GVFrameScheduler::new(ManualClock::new(), 1000, 10.0, DropPolicy::Drop);
+        scheduler.play();
+        scheduler.tick();
+        // consecutive drops are merged
+        for _ in 0..10 {
+            scheduler.clock().advance(ms(300));
+            scheduler.tick();
+        }
+        assert_eq!(scheduler.take_events(), vec![GVClockEvent::FrameDropped { frame_id: 30, count: 20 }]);
+
+        // alternating drops and repeats, only the most recent are kept
+        for _ in 0..100 {
+            scheduler.clock().advance(ms(300));
+            scheduler.tick();
+            scheduler.clock().set(scheduler.clock().elapsed() - ms(100));
+            scheduler.tick();
+        }
+        let events = scheduler.take_events();
+        assert_eq!(events.len(), MAX_EVENTS);
+        assert_eq!(events.last(), Some(&GVClockEvent::FrameRepeated { frame_id: 230 }));
+    }
+
+    #[test]
+    fn hold_policy() {
+        let mut scheduler = GVFrameScheduler::new(ManualClock::new(), 100, 10.0, DropPolicy::Hold { max_lag: 3 });
+        scheduler.play();
+        scheduler.tick();
+        let mut frames = Vec::new();
+        for _ in 0..3 {
+            scheduler.clock().advance(ms(250));
+            frames.push(scheduler.tick());
+        }
+        // clock at 2, 5, 7: falls behind by 1, then 3, then over the limit
+        assert_eq!(frames, vec![1, 2, 7]);
+        assert_eq!(scheduler.take_events(), vec![GVClockEvent::FrameDropped { frame_id: 7, count: 4 }]);
+    }
+
+    #[test]
+    fn external_clock_drift() {
+        let mut clock = ExternalClock::with_correction(ms(100), Duration::from_secs(1));
+        let base = Instant::now();
+        let at = |secs: f64| base + Duration::from_secs_f64(secs);
+        let secs = |d: Duration| d.as_secs_f64();
+
+        // not advancing before the first update, which syncs without event
+        assert_eq!(clock.position_at(at(1.0)), Duration::ZERO);
+        assert_eq!(clock.update_at(Duration::ZERO, at(0.0)), None);
+        assert!((secs(clock.position_at(at(1.0))) - 1.0).abs() < 1e-9);
+
+        // master 50ms ahead: no jump, caught up after correction time
+        let event = clock.update_at(Duration::from_millis(1050), at(1.0));
+        assert!(matches!(event, Some(GVClockEvent::DriftCorrected { drift }) if (drift - 0.05).abs() < 1e-9));
+        assert!((secs(clock.position_at(at(1.0))) - 1.0).abs() < 1e-9);
+        assert!((secs(clock.position_at(at(2.0))) - 2.05).abs() < 1e-9);
+        // normal rate again after the correction time
+        assert!((secs(clock.position_at(at(3.0))) - 3.05).abs() < 1e-9);
+
+        // large drift jumps
+        let event = clock.update_at(Duration::from_secs(5), at(2.0));
+        assert!(matches!(event, Some(GVClockEvent::Resynced { drift }) if (drift - 2.95).abs() < 1e-9));
+        assert!((secs(clock.position_at(at(2.5))) - 5.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn external_clock_pause() {
+        let mut clock = ExternalClock::with_correction(ms(100), Duration::from_secs(1));
+        let base = Instant::now();
+        let at = |secs: f64| base + Duration::from_secs_f64(secs);
+        clock.update_at(Duration::from_secs(5), at(0.0));
+        assert_eq!(clock.position_at(at(0.5)), ms(5500));
+
+        // unchanged position holds the clock, no resync however long the pause
+        assert_eq!(clock.update_at(Duration::from_secs(5), at(0.5)), None);
+        assert_eq!(clock.position_at(at(1.0)), Duration::from_secs(5));
+        assert_eq!(clock.update_at(Duration::from_secs(5), at(2.0)), None);
+        assert_eq!(clock.position_at(at(3.0)), Duration::from_secs(5));
+
+        // resumes from the reported position
+        assert_eq!(clock.update_at(ms(5100), at(3.0)), None);
+        assert!((clock.position_at(at(3.5)).as_secs_f64() - 5.6).abs() < 1e-9);
+        assert!(matches!(clock.update_at(ms(5650), at(3.5)), Some(GVClockEvent::DriftCorrected { .. })));
+    }
+
+    #[test]
+    fn scheduler_clock_events() {
+        let mut scheduler = GVFrameScheduler::new(ExternalClock::new(), 100, 10.0, DropPolicy::Drop);
+        let base = Instant::now();
+        let at = |secs: f64| base + Duration::from_secs_f64(secs);
+        scheduler.update_clock_at(Duration::ZERO, at(0.0));
+        scheduler.update_clock_at(ms(1050), at(1.0));
+        scheduler.update_clock_at(ms(5000), at(2.0));
+        let events = scheduler.take_events();
+        assert!(matches!(events[..], [GVClockEvent::DriftCorrected { .. }, GVClockEvent::Resynced { .. }]));
+    }
+}
@@ -0,0 +1,120 @@
+// shared helpers for BC block encoders
+//
+// input frames are BGRA u32 (0xAARRGGBB), the same representation `GVVideo::read_frame` returns
+
+use std::sync::Mutex;
+
+use crate::GVError;
+
+/// speed / quality preset for BC1, BC2, BC3 color encoding (see `BC7Quality` for BC7)
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum BCQuality {
+    /// range fit: endpoints from the extent of colors along the principal axis
+    #[default]
+    Fast,
+    /// cluster fit: least squares endpoints for every ordering of colors along the principal axis
+    High,
+}
+
+/// gather 4x4 block at block position (bx, by), edge pixels are repeated when width or height is not divisible by 4
+#[inline]
+pub(crate) fn load_block(image: &[u32], width: usize, height: usize, bx: usize, by: usize) -> [u32; 16] {
+    let mut block = [0; 16];
+    for y in 0..4 {
+        let iy = (by * 4 + y).min(height - 1);
+        for x in 0..4 {
+            let ix = (bx * 4 + x).min(width - 1);
+            block[x + y * 4] = image[ix + iy * width];
+        }
+    }
+    block
+}
+
+/// encode every 4x4 block of image in row-major block order
+pub(crate) fn encode_blocks<const N: usize>(
+    image: &[u32],
+    width: usize,
+    height: usize,
+    encode_block: impl Fn(&[u32; 16]) -> [u8; N],
+) -> Result<Vec<u8>, GVError> {
+    if image.len() < width * height {
+        return Err(GVError::InvalidFrameSize { expected: width * height, actual: image.len() });
+    }
+
+    let blocks_x = width.div_ceil(4);
+    let blocks_y = height.div_ceil(4);
+    let mut data = Vec::with_capacity(blocks_x * blocks_y * N);
+    for by in 0..blocks_y {
+        for bx in 0..blocks_x {
+            let block = load_block(image, width, height, bx, by);
+            data.extend_from_slice(&encode_block(&block));
+        }
+    }
+    Ok(data)
+}
+
+/// encode every 4x4 block of image on `threads` threads (0 = available parallelism), block rows are handed out one at a time
+pub(crate) fn encode_blocks_parallel<const N: usize>(
+    image: &[u32],
+    width: usize,
+    height: usize,
+    threads: usize,
+    encode_block: impl Fn(&[u32; 16]) -> [u8; N] + Sync,
+) -> Result<Vec<u8>, GVError> {
+    if image.len() < width * height {
+        return Err(GVError::InvalidFrameSize { expected: width * height, actual: image.len() });
+    }
+
+    let blocks_x = width.div_ceil(4);
+    let blocks_y = height.div_ceil(4);
+    let mut data = vec![0; blocks_x * blocks_y * N];
+    if data.is_empty() {
+        return Ok(data);
+    }
+
+    let threads = match threads {
+        0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    }.min(blocks_y);
+
+    let rows = Mutex::new(data.chunks_mut(blocks_x * N).enumerate());
+    let encode_rows = || loop {
+        let next = rows.lock().unwrap().next();
+        let Some((by, row)) = next else { break };
+        for (bx, out) in row.chunks_mut(N).enumerate() {
+            let block = load_block(image, width, height, bx, by);
+            out.copy_from_slice(&encode_block(&block));
+        }
+    };
+    std::thread::scope(|scope| {
+        for _ in 1..threads {
+            scope.spawn(encode_rows);
+        }
+        encode_rows();
+    });
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_block_repeats_edge() {
+        // 5x5 image, pixel value = x + y * 5
+        let image: Vec<u32> = (0..25).collect();
+        let block = load_block(&image, 5, 5, 1, 1);
+        assert_eq!(block, [24; 16]);
+
+        let block = load_block(&image, 5, 5, 1, 0);
+        assert_eq!(block[0], 4);
+        assert_eq!(block[4], 9);
+        assert_eq!(block[15], 19);
+    }
+
+    #[test]
+    fn image_too_small() {
+        let res = encode_blocks(&[0; 15], 4, 4, |_| [0u8; 8]);
+        assert!(matches!(res, Err(GVError::InvalidFrameSize { expected: 16, actual: 15 })));
+    }
+}
@@ -0,0 +1,87 @@
+use std::{fmt, io};
+
+use crate::GVFormat;
+
+/// Errors returned while loading or decoding a GV video
+#[derive(Debug)]
+pub enum GVError {
+    /// underlying reader failed (other than hitting EOF early)
+    Io(io::Error),
+    /// header contains a format id other than DXT1 = 1, DXT3 = 3, DXT5 = 5, BC7 = 7
+    UnknownFormat(u32),
+    /// file ended before the header, address table or frame data was complete
+    Truncated,
+    /// LZ4 block could not be decompressed
+    Lz4(lz4_flex::block::DecompressError),
+    /// BC block data could not be decoded
+    BlockDecode {
+        format: GVFormat,
+        reason: &'static str,
+    },
+    /// requested frame is not in the video
+    FrameOutOfRange { id: u32, count: u32 },
+    /// frame passed to GVWriter or an encoder is smaller or larger than its width, height and format imply
+    InvalidFrameSize { expected: usize, actual: usize },
+    /// header field or lz4 block size is larger than `GVLoadOptions` allows, or a value does not fit its GV field
+    LimitExceeded { what: &'static str, value: u64, limit: u64 },
+    /// `GVPrefetcher` worker thread panicked, no more frames will be decoded
+    WorkerPanicked,
+    /// timecode could not be parsed, or does not exist at the video's frame rate
+    InvalidTimecode(String),
+    /// lz4 block decompressed to a size other than width, height and format imply
+    /// (`actual` is a lower bound when the output did not fit)
+    Lz4SizeMismatch { id: u32, expected: usize, actual: usize },
+}
+
+impl fmt::Display for GVError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GVError::Io(err) => write!(f, "I/O error: {}", err),
+            GVError::UnknownFormat(format) => write!(f, "Unknown format: {}", format),
+            GVError::Truncated => write!(f, "Unexpected end of file"),
+            GVError::Lz4(err) => write!(f, "Error decompressing LZ4 block: {}", err),
+            GVError::BlockDecode { format, reason } => {
+                write!(f, "Error decoding {:?}: {}", format, reason)
+            }
+            GVError::FrameOutOfRange { id, count } => {
+                write!(f, "Frame {} out of range (frame count: {})", id, count)
+            }
+            GVError::InvalidFrameSize { expected, actual } => {
+                write!(f, "Invalid frame size: expected {}, got {}", expected, actual)
+            }
+            GVError::LimitExceeded { what, value, limit } => {
+                write!(f, "{} {} exceeds limit {}", what, value, limit)
+            }
+            GVError::WorkerPanicked => write!(f, "Prefetch worker thread panicked"),
+            GVError::InvalidTimecode(timecode) => write!(f, "Invalid timecode: {}", timecode),
+            GVError::Lz4SizeMismatch { id, expected, actual } => {
+                write!(f, "LZ4 block of frame {} decompressed to {} bytes, expected {}", id, actual, expected)
+            }
+        }
+    }
+}
+
+impl std::error::Error for GVError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GVError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for GVError {
+    fn from(err: io::Error) -> Self {
+        if err.kind() == io::ErrorKind::UnexpectedEof {
+            GVError::Truncated
+        } else {
+            GVError::Io(err)
+        }
+    }
+}
+
+impl From<lz4_flex::block::DecompressError> for GVError {
+    fn from(err: lz4_flex::block::DecompressError) -> Self {
+        GVError::Lz4(err)
+    }
+}
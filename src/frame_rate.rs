@@ -0,0 +1,175 @@
+// rational frame rate
+//
+// GV stores fps as f32, which cannot represent NTSC rates (29.97 is really 30000/1001), so frame times computed
+// from the float drift against SMPTE timecode. `FrameRate::from_fps` recognizes the intended rational rate,
+// and frame <-> time conversion is done in integer nanoseconds.
+
+use std::time::Duration;
+
+use crate::Rounding;
+
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+/// frames per second as numerator / denominator, e.g. 30000/1001 for 29.97
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct FrameRate {
+    num: u32,
+    den: u32,
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+impl FrameRate {
+    /// reduced to lowest terms, None when denominator is zero
+    pub fn new(num: u32, den: u32) -> Option<FrameRate> {
+        if den == 0 {
+            return None;
+        }
+        let divisor = gcd(num, den).max(1);
+        Some(FrameRate { num: num / divisor, den: den / divisor })
+    }
+
+    /// rate stored in a GV header: integer rates, NTSC rates (n * 1000/1001) and simple fractions are recognized,
+    /// other rates are approximated in 1/1000 steps. zero when fps is not positive.
+    pub fn from_fps(fps: f32) -> FrameRate {
+        if !(fps > 0.0 && fps.is_finite()) {
+            return FrameRate { num: 0, den: 1 };
+        }
+        let fps = fps as f64;
+
+        let n = fps.round();
+        if n >= 1.0 && (fps - n).abs() < 1e-3 {
+            return FrameRate { num: n as u32, den: 1 };
+        }
+
+        // 23.976, 29.97, 59.94, ... are usually stored rounded to 2-3 decimals
+        let n = (fps * 1.001).round();
+        if n >= 1.0 && (fps - n * 1000.0 / 1001.0).abs() < 2e-3 {
+            if let Some(rate) = FrameRate::new((n * 1000.0) as u32, 1001) {
+                return rate;
+            }
+        }
+
+        // simple fraction such as 12.5 = 25/2, within f32 precision
+        for den in 2..=1000u32 {
+            let num = fps * den as f64;
+            if (num - num.round()).abs() <= num * 1e-6 {
+                return FrameRate::new(num.round() as u32, den).unwrap();
+            }
+        }
+        FrameRate::new((fps * 1000.0).round() as u32, 1000).unwrap()
+    }
+
+    pub fn numerator(&self) -> u32 {
+        self.num
+    }
+
+    pub fn denominator(&self) -> u32 {
+        self.den
+    }
+
+    pub fn as_f64(&self) -> f64 {
+        self.num as f64 / self.den as f64
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.num == 0
+    }
+
+    /// NTSC rate (denominator 1001)
+    pub fn is_ntsc(&self) -> bool {
+        self.den == 1001
+    }
+
+    /// start time of frame, rounded to nearest nanosecond
+    pub fn timestamp_of(&self, frame: u64) -> Duration {
+        if self.is_zero() {
+            return Duration::ZERO;
+        }
+        let (num, den) = (self.num as u128, self.den as u128 * NANOS_PER_SEC);
+        let nanos = (2 * frame as u128 * den + num) / (2 * num);
+        let secs = u64::try_from(nanos / NANOS_PER_SEC).unwrap_or(u64::MAX);
+        Duration::new(secs, (nanos % NANOS_PER_SEC) as u32)
+    }
+
+    /// frame at time, exact with respect to `timestamp_of` (frame k starts at `timestamp_of(k)`)
+    pub fn frame_at(&self, time: Duration, rounding: Rounding) -> u64 {
+        if self.is_zero() {
+            return 0;
+        }
+        let (num, den) = (self.num as u128, self.den as u128 * NANOS_PER_SEC);
+        let t = time.as_nanos();
+        // last frame starting at or before t: round(k * den / num) <= t  <=>  k < (2t + 1) * num / 2den
+        let floor = ((2 * t + 1) * num - 1) / (2 * den);
+        let frame = match rounding {
+            Rounding::Floor => floor,
+            // first frame starting at or after t: round(k * den / num) >= t  <=>  k >= (2t - 1) * num / 2den
+            Rounding::Ceil if t == 0 => 0,
+            Rounding::Ceil => ((2 * t - 1) * num).div_ceil(2 * den),
+            Rounding::Nearest => {
+                let start = |k: u128| (2 * k * den + num) / (2 * num);
+                if start(floor + 1) - t <= t - start(floor) { floor + 1 } else { floor }
+            }
+        };
+        u64::try_from(frame).unwrap_or(u64::MAX)
+    }
+}
+
+impl std::fmt::Display for FrameRate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.den == 1 {
+            write!(f, "{}", self.num)
+        } else {
+            write!(f, "{}/{}", self.num, self.den)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rate(num: u32, den: u32) -> FrameRate {
+        FrameRate::new(num, den).unwrap()
+    }
+
+    #[test]
+    fn recognize() {
+        assert_eq!(FrameRate::from_fps(30.0), rate(30, 1));
+        assert_eq!(FrameRate::from_fps(1.0), rate(1, 1));
+        assert_eq!(FrameRate::from_fps(29.97), rate(30000, 1001));
+        assert_eq!(FrameRate::from_fps(30000.0 / 1001.0), rate(30000, 1001));
+        assert_eq!(FrameRate::from_fps(23.976), rate(24000, 1001));
+        assert_eq!(FrameRate::from_fps(59.94), rate(60000, 1001));
+        assert_eq!(FrameRate::from_fps(119.88), rate(120000, 1001));
+        assert_eq!(FrameRate::from_fps(12.5), rate(25, 2));
+        assert_eq!(FrameRate::from_fps(7.3), rate(73, 10));
+        assert_eq!(FrameRate::from_fps(0.0), FrameRate::new(0, 1).unwrap());
+        assert_eq!(FrameRate::from_fps(f32::NAN), FrameRate::new(0, 1).unwrap());
+
+        assert!(FrameRate::from_fps(29.97).is_ntsc());
+        assert_eq!(FrameRate::from_fps(29.97).to_string(), "30000/1001");
+        assert_eq!(rate(60, 2), rate(30, 1));
+        assert!(FrameRate::new(1, 0).is_none());
+    }
+
+    #[test]
+    fn exact_ntsc_timing() {
+        let ntsc = rate(30000, 1001);
+        // 30000 frames take exactly 1001 seconds
+        assert_eq!(ntsc.timestamp_of(30000), Duration::from_secs(1001));
+        assert_eq!(ntsc.frame_at(Duration::from_secs(1001), Rounding::Floor), 30000);
+        // 24h of 29.97 content, frame boundaries are exact
+        let frames = 86400 * 30000 / 1001;
+        for k in frames - 5..frames + 5 {
+            let t = ntsc.timestamp_of(k);
+            assert_eq!(ntsc.frame_at(t, Rounding::Floor), k);
+            assert_eq!(ntsc.frame_at(t, Rounding::Ceil), k);
+            assert_eq!(ntsc.frame_at(t, Rounding::Nearest), k);
+            assert_eq!(ntsc.frame_at(t - Duration::from_nanos(1), Rounding::Floor), k - 1);
+            assert_eq!(ntsc.frame_at(t + Duration::from_nanos(1), Rounding::Ceil), k + 1);
+        }
+    }
+}
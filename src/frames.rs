@@ -0,0 +1,164 @@
+use std::{
+    io::{Read, Seek},
+    iter::FusedIterator,
+    ops::{Bound, Range, RangeBounds},
+    time::Duration,
+};
+
+use crate::{GVError, GVVideo};
+
+/// frame yielded by `GVVideo::frames()` and `GVVideo::compressed_frames()`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct GVFrame<T> {
+    pub id: u32,
+    pub timestamp: Duration,
+    /// BGRA u32 for `frames()`, BC compressed data for `compressed_frames()`
+    pub data: T,
+}
+
+/// iterator over frames of a video, see `GVVideo::frames()`
+pub struct GVFrames<'a, Reader: Read + Seek, T> {
+    video: &'a mut GVVideo<Reader>,
+    range: Range<u32>,
+    read: fn(&mut GVVideo<Reader>, u32) -> Result<T, GVError>,
+}
+
+impl<Reader: Read + Seek, T> GVFrames<'_, Reader, T> {
+    fn read(&mut self, id: u32) -> Result<GVFrame<T>, GVError> {
+        let data = (self.read)(self.video, id)?;
+        let timestamp = self.video.timestamp_of(id);
+        Ok(GVFrame { id, timestamp, data })
+    }
+}
+
+impl<Reader: Read + Seek, T> Iterator for GVFrames<'_, Reader, T> {
+    type Item = Result<GVFrame<T>, GVError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let id = self.range.next()?;
+        Some(self.read(id))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.range.size_hint()
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        // random access, skipped frames are not read
+        let id = self.range.nth(n)?;
+        Some(self.read(id))
+    }
+}
+
+impl<Reader: Read + Seek, T> DoubleEndedIterator for GVFrames<'_, Reader, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let id = self.range.next_back()?;
+        Some(self.read(id))
+    }
+
+    fn nth_back(&mut self, n: usize) -> Option<Self::Item> {
+        let id = self.range.nth_back(n)?;
+        Some(self.read(id))
+    }
+}
+
+impl<Reader: Read + Seek, T> ExactSizeIterator for GVFrames<'_, Reader, T> {}
+
+impl<Reader: Read + Seek, T> FusedIterator for GVFrames<'_, Reader, T> {}
+
+impl<Reader: Read + Seek> GVVideo<Reader> {
+    /// clamp range of frame ids to the video
+    pub(crate) fn frame_range(&self, range: impl RangeBounds<u32>) -> Range<u32> {
+        let count = self.header.frame_count;
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start.saturating_add(1),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end.saturating_add(1),
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => count,
+        };
+        start.min(count)..end.min(count).max(start.min(count))
+    }
+
+    /// iterate all frames (decompressed BGRA u32, same as `read_frame`)
+    pub fn frames(&mut self) -> GVFrames<'_, Reader, Vec<u32>> {
+        self.frames_in(..)
+    }
+
+    /// iterate frames in range (clamped to frame count), e.g. `frames_in(10..50)`
+    pub fn frames_in(&mut self, range: impl RangeBounds<u32>) -> GVFrames<'_, Reader, Vec<u32>> {
+        let range = self.frame_range(range);
+        GVFrames { video: self, range, read: GVVideo::read_frame }
+    }
+
+    /// iterate all frames (BC compressed, same as `read_frame_compressed`)
+    pub fn compressed_frames(&mut self) -> GVFrames<'_, Reader, Vec<u8>> {
+        self.compressed_frames_in(..)
+    }
+
+    /// iterate BC compressed frames in range (clamped to frame count)
+    pub fn compressed_frames_in(&mut self, range: impl RangeBounds<u32>) -> GVFrames<'_, Reader, Vec<u8>> {
+        let range = self.frame_range(range);
+        GVFrames { video: self, range, read: GVVideo::read_frame_compressed }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    // 10px 5sec 1fps
+    const TEST_10PX_GV: &[u8; 474] = include_bytes!("../test_asset/test-10px.gv");
+
+    #[test]
+    fn iterate_frames() {
+        let mut video = GVVideo::load(Cursor::new(TEST_10PX_GV)).unwrap();
+        let expected: Vec<Vec<u32>> = (0..5).map(|i| video.read_frame(i).unwrap()).collect();
+
+        let frames = video.frames();
+        assert_eq!(frames.len(), 5);
+        for (i, frame) in frames.enumerate() {
+            let frame = frame.unwrap();
+            assert_eq!(frame.id, i as u32);
+            assert_eq!(frame.timestamp, Duration::from_secs(i as u64));
+            assert_eq!(frame.data, expected[i]);
+        }
+    }
+
+    #[test]
+    fn iterate_compressed_frames_reversed() {
+        let mut video = GVVideo::load(Cursor::new(TEST_10PX_GV)).unwrap();
+        let ids: Vec<u32> = video.compressed_frames().rev().map(|f| f.unwrap().id).collect();
+        assert_eq!(ids, vec![4, 3, 2, 1, 0]);
+
+        let frame = video.compressed_frames().next().unwrap().unwrap();
+        assert_eq!(frame.data, video.read_frame_compressed(0).unwrap());
+    }
+
+    #[test]
+    fn iterate_range() {
+        let mut video = GVVideo::load(Cursor::new(TEST_10PX_GV)).unwrap();
+        let ids: Vec<u32> = video.frames_in(1..3).map(|f| f.unwrap().id).collect();
+        assert_eq!(ids, vec![1, 2]);
+
+        let ids: Vec<u32> = video.frames_in(3..).map(|f| f.unwrap().id).collect();
+        assert_eq!(ids, vec![3, 4]);
+
+        let ids: Vec<u32> = video.compressed_frames_in(..=1).map(|f| f.unwrap().id).collect();
+        assert_eq!(ids, vec![0, 1]);
+
+        // clamped to frame count
+        assert_eq!(video.frames_in(2..100).len(), 3);
+        assert_eq!(video.frames_in(10..20).len(), 0);
+
+        let mut frames = video.frames_in(..);
+        assert_eq!(frames.nth(3).unwrap().unwrap().id, 3);
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames.next_back().unwrap().unwrap().id, 4);
+        assert!(frames.next().is_none());
+    }
+}
@@ -13,12 +13,56 @@
 //
 
 
+mod bc1_encoder;
 mod bc2_decoder;
+mod bc2_encoder;
+mod bc3_encoder;
+mod bc7_encoder;
+mod cache;
+mod clock;
+mod encoder;
+mod error;
+mod frame_rate;
+mod frames;
+mod load_options;
+mod mapped;
+#[cfg(feature = "rayon")]
+mod parallel_decoder;
+mod player;
+mod preload;
+mod prefetcher;
+mod probe;
+mod recover;
+mod shared;
+mod timecode;
+mod timing;
+mod validate;
+mod writer;
 
 use std::{fs::File, io::{BufReader, Read, Seek}, mem};
 
 use byteorder::{LittleEndian, ReadBytesExt};
-use texture2ddecoder;
+
+pub use bc1_encoder::{encode_bc1, encode_bc1_block};
+pub use bc2_encoder::{encode_bc2, encode_bc2_block};
+pub use bc3_encoder::{encode_bc3, encode_bc3_block};
+pub use bc7_encoder::{encode_bc7, encode_bc7_block, BC7Options, BC7Quality};
+pub use cache::{GVCacheStats, GVFrameCache};
+pub use clock::{DropPolicy, ExternalClock, GVClock, GVClockEvent, GVFrameScheduler, ManualClock, SystemClock};
+pub use encoder::BCQuality;
+pub use error::GVError;
+pub use frame_rate::FrameRate;
+pub use frames::{GVFrame, GVFrames};
+pub use load_options::GVLoadOptions;
+pub use player::{GVPlayer, LoopMode};
+pub use preload::GVMemoryUsage;
+pub use prefetcher::{GVPrefetchOptions, GVPrefetcher, PrefetchDirection};
+pub use probe::{is_probably_gv, GVInfo};
+pub use shared::{ReadAt, SharedGVVideo};
+pub use timecode::Timecode;
+pub use timing::Rounding;
+pub use validate::{GVIssue, GVValidationReport};
+pub use writer::{write_header, GVWriter};
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum GVFormat {
@@ -28,9 +72,26 @@ pub enum GVFormat {
     BC7 = 7,
 }
 
-// const HEADER_SIZE: usize = 24;
+impl GVFormat {
+    /// bytes of one 4x4 block (8 for DXT1, 16 for others)
+    pub fn block_bytes(&self) -> usize {
+        match self {
+            GVFormat::DXT1 => 8,
+            GVFormat::DXT3 | GVFormat::DXT5 | GVFormat::BC7 => 16,
+        }
+    }
 
-#[derive(Debug)]
+    /// bytes of one BC compressed frame (width and height are rounded up to multiple of 4)
+    pub fn frame_bytes(&self, width: u32, height: u32) -> usize {
+        let blocks_x = width.div_ceil(4) as usize;
+        let blocks_y = height.div_ceil(4) as usize;
+        blocks_x * blocks_y * self.block_bytes()
+    }
+}
+
+const HEADER_SIZE: usize = 24;
+
+#[derive(Debug, PartialEq, Clone)]
 pub struct GVHeader {
     pub width: u32,
     pub height: u32,
@@ -40,6 +101,15 @@ pub struct GVHeader {
     pub frame_bytes: u32,
 }
 
+impl GVHeader {
+    /// size of one BC compressed frame implied by width, height and format, which lz4 blocks decompress to
+    ///
+    /// used instead of `frame_bytes`, which is not checked when loading (see `GVVideo::validate`)
+    pub fn expected_frame_bytes(&self) -> usize {
+        self.format.frame_bytes(self.width, self.height)
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub struct GVAddressSizeBlock {
     pub address: u64,
@@ -51,6 +121,31 @@ pub struct GVVideo<Reader: Read + Seek> {
     pub header: GVHeader,
     pub address_size_blocks: Vec<GVAddressSizeBlock>,
     pub reader: Reader,
+    scratch: Scratch,
+    /// lz4 blocks held in memory, see `preload()`
+    preloaded: Option<preload::Preloaded>,
+    /// limits checked on frame reads
+    options: GVLoadOptions,
+    /// reader size at load time, lz4 blocks must lie within it
+    file_size: u64,
+}
+
+/// buffers reused across frame reads, so steady-state `read_frame_into` does not allocate
+#[derive(Default)]
+struct Scratch {
+    /// lz4 compressed frame
+    lz4: Vec<u8>,
+    /// lz4 decompressed (BC compressed) frame
+    bc: Vec<u8>,
+}
+
+impl std::fmt::Debug for Scratch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Scratch")
+            .field("lz4_capacity", &self.lz4.capacity())
+            .field("bc_capacity", &self.bc.capacity())
+            .finish()
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -89,14 +184,17 @@ pub fn get_alpha(color: u32) -> u8 {
     (color >> 24) as u8
 }
 
+#[allow(clippy::ptr_arg)]
 pub fn get_rgba_from_frame(frame: &Vec<u32>, x: usize, y: usize, width: usize) -> RGBAColor {
     get_rgba(frame[x + y * width])
 }
 
+#[allow(clippy::ptr_arg)]
 pub fn get_rgb_from_frame(frame: &Vec<u32>, x: usize, y: usize, width: usize) -> RGBColor {
     get_rgb(frame[x + y * width])
 }
 
+#[allow(clippy::ptr_arg)]
 pub fn get_alpha_from_frame(frame: &Vec<u32>, x: usize, y: usize, width: usize) -> u8 {
     get_alpha(frame[x + y * width])
 }
@@ -109,7 +207,7 @@ pub fn get_rgba_vec_from_frame(frame: &Vec<u32>) -> Vec<u8> {
     for color in frame {
         result.push((color >> 16) as u8);
         result.push((color >> 8) as u8);
-        result.push((color >> 0) as u8);
+        result.push(*color as u8);
         result.push((color >> 24) as u8);
     }
     result
@@ -123,7 +221,7 @@ pub fn get_rgb_vec_from_frame(frame: &Vec<u32>) -> Vec<u8> {
     for color in frame {
         result.push((color >> 16) as u8);
         result.push((color >> 8) as u8);
-        result.push((color >> 0) as u8);
+        result.push(*color as u8);
     }
     result
 }
@@ -132,7 +230,7 @@ pub fn get_rgb_vec_from_frame(frame: &Vec<u32>) -> Vec<u8> {
 /// faster but unsafe
 pub fn to_vec_u8_unsafe(mut frame: Vec<u32>) -> Vec<u8> {
     // https://stackoverflow.com/questions/49690459/converting-a-vecu32-to-vecu8-in-place-and-with-minimal-overhead
-    let vec8 = unsafe {
+    unsafe {
         let ratio = mem::size_of::<u32>() / mem::size_of::<u8>();
 
         let length = frame.len() * ratio;
@@ -144,9 +242,7 @@ pub fn to_vec_u8_unsafe(mut frame: Vec<u32>) -> Vec<u8> {
 
         // Construct new Vec
         Vec::from_raw_parts(ptr, length, capacity)
-    };
-
-    vec8
+    }
 }
 
 /// BGRA u32 -> BGRA u8
@@ -154,7 +250,7 @@ pub fn to_vec_u8_unsafe(mut frame: Vec<u32>) -> Vec<u8> {
 pub fn to_vec_u8_safe(frame: Vec<u32>) -> Vec<u8> {
     let mut result = Vec::with_capacity(frame.len() * 4);
     for color in frame {
-        result.push((color >> 0) as u8);
+        result.push(color as u8);
         result.push((color >> 8) as u8);
         result.push((color >> 16) as u8);
         result.push((color >> 24) as u8);
@@ -173,14 +269,14 @@ pub fn get_bgra_vec_from_frame(frame: Vec<u32>) -> Vec<u8> {
     }
 }
 
-pub fn read_header<Reader>(reader: &mut Reader) -> GVHeader where Reader: std::io::Read {
-    let width = reader.read_u32::<LittleEndian>().unwrap();
-    let height = reader.read_u32::<LittleEndian>().unwrap();
-    let frame_count = reader.read_u32::<LittleEndian>().unwrap();
-    let fps = reader.read_f32::<LittleEndian>().unwrap();
-    let format = reader.read_u32::<LittleEndian>().unwrap();
-    let frame_bytes = reader.read_u32::<LittleEndian>().unwrap();
-    GVHeader {
+pub fn read_header<Reader>(reader: &mut Reader) -> Result<GVHeader, GVError> where Reader: std::io::Read {
+    let width = reader.read_u32::<LittleEndian>()?;
+    let height = reader.read_u32::<LittleEndian>()?;
+    let frame_count = reader.read_u32::<LittleEndian>()?;
+    let fps = reader.read_f32::<LittleEndian>()?;
+    let format = reader.read_u32::<LittleEndian>()?;
+    let frame_bytes = reader.read_u32::<LittleEndian>()?;
+    Ok(GVHeader {
         width,
         height,
         frame_count,
@@ -190,42 +286,91 @@ pub fn read_header<Reader>(reader: &mut Reader) -> GVHeader where Reader: std::i
             3 => GVFormat::DXT3,
             5 => GVFormat::DXT5,
             7 => GVFormat::BC7,
-            _ => panic!("Unknown format"),
+            _ => return Err(GVError::UnknownFormat(format)),
         },
         frame_bytes,
+    })
+}
+
+/// decode BC1, BC2, BC3, BC7 data into BGRA u32 buffer (at least width * height)
+fn decode_dxt_into(data: &[u8], width: usize, height: usize, format: GVFormat, result: &mut [u32]) -> Result<(), GVError> {
+    let res = match format {
+        GVFormat::DXT1 => texture2ddecoder::decode_bc1(data, width, height, result),
+        // texture2ddecoder::decode_bc2 is not released yet
+        GVFormat::DXT3 => bc2_decoder::decode_bc2(data, width, height, result),
+        GVFormat::DXT5 => texture2ddecoder::decode_bc3(data, width, height, result),
+        GVFormat::BC7 => texture2ddecoder::decode_bc7(data, width, height, result),
+    };
+
+    res.map_err(|reason| GVError::BlockDecode { format, reason })
+}
+
+/// decompress lz4 block of frame into `buffer` (at least `expected` bytes), then return its length
+///
+/// output other than exactly `expected` bytes is `GVError::Lz4SizeMismatch`
+fn decompress_frame(frame_id: u32, lz4: &[u8], buffer: &mut [u8], expected: usize) -> Result<usize, GVError> {
+    if buffer.len() < expected {
+        return Err(GVError::InvalidFrameSize { expected, actual: buffer.len() });
+    }
+    match lz4_flex::block::decompress_into(lz4, buffer) {
+        Ok(len) if len == expected => Ok(len),
+        Ok(actual) => Err(GVError::Lz4SizeMismatch { id: frame_id, expected, actual }),
+        // output is longer than the buffer, `expected` of the error is a lower bound of the output size
+        Err(lz4_flex::block::DecompressError::OutputTooSmall { expected: actual, .. }) => {
+            Err(GVError::Lz4SizeMismatch { id: frame_id, expected, actual })
+        }
+        Err(err) => Err(err.into()),
     }
 }
 
+/// decode BC compressed frame data of the video into `buffer` (BGRA u32, at least width * height)
+fn decode_bc(header: &GVHeader, data: &[u8], buffer: &mut [u32]) -> Result<(), GVError> {
+    let width = header.width as usize;
+    let height = header.height as usize;
+
+    #[cfg(feature = "rayon")]
+    let res = parallel_decoder::decode_dxt_parallel(data, width, height, header.format, buffer);
+    #[cfg(not(feature = "rayon"))]
+    let res = decode_dxt_into(data, width, height, header.format, buffer);
+    res
+}
+
 impl<Reader: Read + Seek> GVVideo<Reader> {
-    pub fn load(mut reader: Reader) -> GVVideo<Reader> {
-        let header = read_header(&mut reader);
-        let address_size_blocks = GVVideo::get_address_size_blocks(header.frame_count, reader.by_ref());
-        GVVideo {
+    /// load header and address table with default `GVLoadOptions` limits
+    pub fn load(reader: Reader) -> Result<GVVideo<Reader>, GVError> {
+        GVVideo::load_with_options(reader, GVLoadOptions::default())
+    }
+
+    /// load header and address table, failing with `GVError::LimitExceeded` when the header exceeds `options`
+    pub fn load_with_options(mut reader: Reader, options: GVLoadOptions) -> Result<GVVideo<Reader>, GVError> {
+        let header = read_header(&mut reader)?;
+        options.check_header(&header)?;
+        let (address_size_blocks, file_size) = GVVideo::get_address_size_blocks(header.frame_count, reader.by_ref())?;
+        Ok(GVVideo {
             header,
             address_size_blocks,
             reader,
-        }
-    }
-
-    pub fn load_from_file(file_path: &str) -> GVVideo<BufReader<File>> {
-        let file = File::open(file_path).unwrap();
-        let reader = BufReader::new(file);
-        GVVideo::load(reader)
+            scratch: Scratch::default(),
+            preloaded: None,
+            options,
+            file_size,
+        })
     }
 
-    fn get_address_size_blocks(frame_count: u32, mut reader: Reader) -> Vec<GVAddressSizeBlock> {
-        let mut address_size_blocks = Vec::new();
-
-        // println!("frame_count: {}", frame_count);
-        // println!("seek: {}", -((frame_count * 16) as i64));
-
+    /// read address table, then return it with the file size
+    fn get_address_size_blocks(frame_count: u32, mut reader: Reader) -> Result<(Vec<GVAddressSizeBlock>, u64), GVError> {
         // seek to top of address_size_blocks
-        reader.seek(std::io::SeekFrom::End(
-            -((frame_count * 16) as i64)
-        )).unwrap();
+        let file_size = reader.seek(std::io::SeekFrom::End(0))?;
+        let table_size = frame_count as u64 * 16;
+        if file_size < HEADER_SIZE as u64 + table_size {
+            return Err(GVError::Truncated);
+        }
+        // allocate only after the table is known to fit in the file
+        let mut address_size_blocks = Vec::with_capacity(frame_count as usize);
+        reader.seek(std::io::SeekFrom::Start(file_size - table_size))?;
         for _ in 0..frame_count {
-            let address = reader.read_u64::<LittleEndian>().unwrap();
-            let size = reader.read_u64::<LittleEndian>().unwrap();
+            let address = reader.read_u64::<LittleEndian>()?;
+            let size = reader.read_u64::<LittleEndian>()?;
             address_size_blocks.push(GVAddressSizeBlock { address, size });
         }
         // seek to first frame
@@ -233,171 +378,106 @@ impl<Reader: Read + Seek> GVVideo<Reader> {
             let address = address_size_blocks[0].address;
             let _ = reader.seek(std::io::SeekFrom::Start(address));
         }
-        address_size_blocks
+        Ok((address_size_blocks, file_size))
     }
 
-    fn decode_lz4(&mut self, data: Vec<u8>) -> Vec<u8> {
-        let width = self.header.width as usize;
-        let height = self.header.height as usize;
-        let uncompressed_size = (width * height * 4) as usize;
-        let lz4_decoded_data = lz4_flex::block::decompress(&data, uncompressed_size).unwrap();
-        lz4_decoded_data
+    /// lz4 decompressed frame size, see `GVHeader::expected_frame_bytes`
+    fn uncompressed_size(&self) -> usize {
+        self.header.expected_frame_bytes()
     }
 
     /// only for testing
-    fn _decode_dxt(&mut self, data: Vec<u8>) -> Vec<u32> {
+    fn _decode_dxt(&mut self, data: Vec<u8>) -> Result<Vec<u32>, GVError> {
         let width = self.header.width as usize;
         let height = self.header.height as usize;
-        let format = self.header.format;
-        let uncompressed_size_u32 = (width * height) as usize;
-        let mut result = vec![0; uncompressed_size_u32];
-
-        match format {
-            GVFormat::DXT1 => {
-                let res = texture2ddecoder::decode_bc1(&data, width, height, &mut result);
-                if res.is_err() {
-                    panic!("Error decoding DXT1: {:?}", res.err().unwrap());
-                }else{
-                    result
-                }
-            }
-            GVFormat::DXT3 => {
-                // let res = texture2ddecoder::decode_bc2(&data, width, height, &mut result);
-                let res = bc2_decoder::decode_bc2(&data, width, height, &mut result);
-                if res.is_err() {
-                    panic!("Error decoding DXT3: {:?}", res.err().unwrap());
-                }else{
-                    result
-                }
-            }
-            GVFormat::DXT5 => {
-                let res = texture2ddecoder::decode_bc3(&data, width, height, &mut result);
-                if res.is_err() {
-                    panic!("Error decoding DXT5: {:?}", res.err().unwrap());
-                }else{
-                    result
-                }
-            }
-            GVFormat::BC7 => {
-                let res = texture2ddecoder::decode_bc7(&data, width, height, &mut result);
-                if res.is_err() {
-                    panic!("Error decoding BC7: {:?}", res.err().unwrap());
-                }else{
-                    result
-                }
-            }
-        }
-    }
-
-    fn decode_lz4_and_dxt(&mut self, data: Vec<u8>) -> Vec<u32> {
-        let width = self.header.width as usize;
-        let height = self.header.height as usize;
-        let format = self.header.format;
-        let uncompressed_size_u8 = (width * height * 4) as usize;
-        let uncompressed_size_u32 = (width * height) as usize;
-        let lz4_decoded_data = lz4_flex::block::decompress(&data, uncompressed_size_u8).unwrap();
-        let mut result = vec![0; uncompressed_size_u32];
-
-        match format {
-            GVFormat::DXT1 => {
-                let res = texture2ddecoder::decode_bc1(&lz4_decoded_data, width, height, &mut result);
-                if res.is_err() {
-                    panic!("Error decoding DXT1: {:?}", res.err().unwrap());
-                }else{
-                    result
-                }
-            }
-            GVFormat::DXT3 => {
-                let res = bc2_decoder::decode_bc2(&lz4_decoded_data, width, height, &mut result);
-                if res.is_err() {
-                    panic!("Error decoding DXT3: {:?}", res.err().unwrap());
-                }else{
-                    result
-                }
-            }
-            GVFormat::DXT5 => {
-                let res = texture2ddecoder::decode_bc3(&lz4_decoded_data, width, height, &mut result);
-                if res.is_err() {
-                    panic!("Error decoding DXT5: {:?}", res.err().unwrap());
-                }else{
-                    result
-                }
-            }
-            GVFormat::BC7 => {
-                let res = texture2ddecoder::decode_bc7(&lz4_decoded_data, width, height, &mut result);
-                if res.is_err() {
-                    panic!("Error decoding BC7: {:?}", res.err().unwrap());
-                }else{
-                    result
-                }
-            }
-        }
+        let mut result = vec![0; width * height];
+        decode_dxt_into(&data, width, height, self.header.format, &mut result)?;
+        Ok(result)
     }
 
-    /// decompress lz4 block and decode dxt, then return decompressed frame data (BGRA u32)
-    pub fn read_frame(&mut self, frame_id: u32) -> Result<Vec<u32>, &'static str> {
+    /// read raw lz4 block of the frame into scratch buffer, unless it is preloaded
+    fn read_lz4_block(&mut self, frame_id: u32) -> Result<(), GVError> {
         if frame_id >= self.header.frame_count {
-            return Err("End of video");
+            return Err(GVError::FrameOutOfRange { id: frame_id, count: self.header.frame_count });
+        }
+        if self.is_preloaded(frame_id) {
+            return Ok(());
         }
-
-        // println!("frame_id: {}", frame_id);
-        // println!("debug: {}", -((self.header.frame_count * 16) as i64) + (frame_id as i64 * 16));
 
         let block = self.address_size_blocks[frame_id as usize];
         let address = block.address;
-        let size = block.size as usize;
-        
-        // println!("address: {}", address);
-        // println!("size: {}", size);
-        
-        let mut data = vec![0; size];
-
-        if let Err(_) = self.reader.seek(std::io::SeekFrom::Start(address)) {
-            return Err("Error seeking frame data");
-        }
-        if let Err(_) = self.reader.read_exact(&mut data) {
-            return Err("Error reading frame data");
+        let size = self.options.check_block(&block)?;
+        // corrupt address table must not cause huge allocation
+        if address.checked_add(block.size).map_or(true, |end| end > self.file_size) {
+            return Err(GVError::Truncated);
         }
 
-        Ok(self.decode_lz4_and_dxt(data))
+        self.scratch.lz4.resize(size, 0);
+
+        self.reader.seek(std::io::SeekFrom::Start(address))?;
+        self.reader.read_exact(&mut self.scratch.lz4)?;
+
+        Ok(())
     }
 
-    /// decompress lz4 block, then return compressed frame data (BC1, BC2, BC3, BC7)
-    pub fn read_frame_compressed(&mut self, frame_id: u32) -> Result<Vec<u8>, &'static str> {
-        if frame_id >= self.header.frame_count {
-            return Err("End of video");
+    /// decompress lz4 block and decode dxt, then return decompressed frame data (BGRA u32)
+    pub fn read_frame(&mut self, frame_id: u32) -> Result<Vec<u32>, GVError> {
+        let width = self.header.width as usize;
+        let height = self.header.height as usize;
+        let mut result = vec![0; width * height];
+        self.read_frame_into(frame_id, &mut result)?;
+        Ok(result)
+    }
+
+    /// decompress lz4 block and decode dxt into `buffer` (BGRA u32, at least width * height)
+    ///
+    /// internal buffers are reused, so this does not allocate once they have grown to the largest frame
+    pub fn read_frame_into(&mut self, frame_id: u32, buffer: &mut [u32]) -> Result<(), GVError> {
+        let width = self.header.width as usize;
+        let height = self.header.height as usize;
+        if buffer.len() < width * height {
+            return Err(GVError::InvalidFrameSize { expected: width * height, actual: buffer.len() });
         }
 
-        let block = self.address_size_blocks[frame_id as usize];
-        let address = block.address;
-        let size = block.size as usize;
-        
-        let mut data = vec![0; size];
+        self.read_lz4_block(frame_id)?;
+        let uncompressed_size = self.uncompressed_size();
+        self.scratch.bc.resize(uncompressed_size, 0);
+        let lz4 = self.preloaded.as_ref().and_then(|p| p.block(frame_id)).unwrap_or(&self.scratch.lz4);
+        decompress_frame(frame_id, lz4, &mut self.scratch.bc, uncompressed_size)?;
+        decode_bc(&self.header, &self.scratch.bc, buffer)
+    }
 
-        if let Err(_) = self.reader.seek(std::io::SeekFrom::Start(address)) {
-            return Err("Error seeking frame data");
-        }
-        if let Err(_) = self.reader.read_exact(&mut data) {
-            return Err("Error reading frame data");
-        }
+    /// decompress lz4 block, then return compressed frame data (BC1, BC2, BC3, BC7)
+    pub fn read_frame_compressed(&mut self, frame_id: u32) -> Result<Vec<u8>, GVError> {
+        let mut result = vec![0; self.uncompressed_size()];
+        self.read_frame_compressed_into(frame_id, &mut result)?;
+        Ok(result)
+    }
 
-        Ok(self.decode_lz4(data))
+    /// decompress lz4 block into `buffer` (at least `GVHeader::expected_frame_bytes`), then return its length
+    /// (compressed frame data, BC1, BC2, BC3, BC7)
+    ///
+    /// internal buffer is reused, so this does not allocate once it has grown to the largest lz4 block
+    pub fn read_frame_compressed_into(&mut self, frame_id: u32, buffer: &mut [u8]) -> Result<usize, GVError> {
+        self.read_lz4_block(frame_id)?;
+        let lz4 = self.preloaded.as_ref().and_then(|p| p.block(frame_id)).unwrap_or(&self.scratch.lz4);
+        decompress_frame(frame_id, lz4, buffer, self.uncompressed_size())
     }
 
     /// decompress lz4 block and decode dxt, then return decompressed frame data (BGRA u32), at specified time
-    pub fn read_frame_at(&mut self, duration: std::time::Duration) -> Result<Vec<u32>, &'static str> {
-        let frame_id = (self.header.fps * duration.as_secs_f32()) as u32;
+    pub fn read_frame_at(&mut self, duration: std::time::Duration) -> Result<Vec<u32>, GVError> {
+        let frame_id = self.frame_at(duration, Rounding::Floor);
         self.read_frame(frame_id)
     }
 
     /// decompress lz4 block, then return compressed frame data (BC1, BC2, BC3, BC7), at specified time
-    pub fn read_frame_compressed_at(&mut self, duration: std::time::Duration) -> Result<Vec<u8>, &'static str> {
-        let frame_id = (self.header.fps * duration.as_secs_f32()) as u32;
+    pub fn read_frame_compressed_at(&mut self, duration: std::time::Duration) -> Result<Vec<u8>, GVError> {
+        let frame_id = self.frame_at(duration, Rounding::Floor);
         self.read_frame_compressed(frame_id)
     }
 
     pub fn get_duration(&self) -> std::time::Duration {
-        std::time::Duration::from_secs_f32(self.header.frame_count as f32 / self.header.fps)
+        self.header.duration()
     }
 
     pub fn get_width(&self) -> u32 {
@@ -429,6 +509,14 @@ impl<Reader: Read + Seek> GVVideo<Reader> {
     }
 }
 
+impl GVVideo<BufReader<File>> {
+    pub fn load_from_file(file_path: &str) -> Result<GVVideo<BufReader<File>>, GVError> {
+        let file = File::open(file_path)?;
+        let reader = BufReader::new(file);
+        GVVideo::load(reader)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -457,7 +545,7 @@ mod tests {
             0x00, 0x00, 0x00, 0x00, // 2
             0x00, 0x00, 0x00, 0x00, // 3
         ];
-        let address_size_blocks = vec![
+        let address_size_blocks = [
             GVAddressSizeBlock { address: header_data.len() as u64, size: frame_data.len() as u64 },
             GVAddressSizeBlock { address: header_data.len() as u64 + frame_data.len() as u64, size: frame_data.len() as u64 },
         ];
@@ -469,7 +557,7 @@ mod tests {
         data.extend_from_slice(&address_size_blocks[1].address.to_le_bytes());
         data.extend_from_slice(&address_size_blocks[1].size.to_le_bytes());
         let mut reader = Cursor::new(data);
-        let video = GVVideo::load(&mut reader);
+        let video = GVVideo::load(&mut reader).unwrap();
         assert_eq!(video.header.width, 2);
         assert_eq!(video.header.height, 2);
         assert_eq!(video.header.frame_count, 2);
@@ -482,7 +570,7 @@ mod tests {
     fn header_read_with_file() {
         let data = TEST_GV;
         let mut reader = Cursor::new(data);
-        let video = GVVideo::load(&mut reader);
+        let video = GVVideo::load(&mut reader).unwrap();
         assert_eq!(video.header.width, 640);
         assert_eq!(video.header.height, 360);
         assert_eq!(video.header.frame_count, 1);
@@ -495,7 +583,7 @@ mod tests {
     fn read_first_frame() {
         let data = TEST_GV;
         let mut reader = Cursor::new(data);
-        let mut video = GVVideo::load(&mut reader);
+        let mut video = GVVideo::load(&mut reader).unwrap();
         let frame = video.read_frame(0).unwrap();
         assert_eq!(frame.len(), 640 * 360);
     }
@@ -504,10 +592,10 @@ mod tests {
     fn read_first_frame_compressed() {
         let data = TEST_GV;
         let mut reader = Cursor::new(data);
-        let mut video = GVVideo::load(&mut reader);
+        let mut video = GVVideo::load(&mut reader).unwrap();
         let frame_bc = video.read_frame_compressed(0).unwrap();
         let frame_raw_right = video.read_frame(0).unwrap();
-        let frame_raw = video._decode_dxt(frame_bc);
+        let frame_raw = video._decode_dxt(frame_bc).unwrap();
 
         assert_eq!(frame_raw.len(), 640 * 360);
         assert_eq!(frame_raw.len(), frame_raw_right.len());
@@ -518,7 +606,7 @@ mod tests {
     fn read_rgba() {
         let data = TEST_GV;
         let mut reader = Cursor::new(data);
-        let mut video = GVVideo::load(&mut reader);
+        let mut video = GVVideo::load(&mut reader).unwrap();
         let frame = video.read_frame(0).unwrap();
         // rgba: 189, 190, 189, 255
         assert_eq!(frame[0], 0xFFBDBEBD);
@@ -560,17 +648,17 @@ mod tests {
     fn read_second_frame_then_error() {
         let data = TEST_GV;
         let mut reader = Cursor::new(data);
-        let mut video = GVVideo::load(&mut reader);
+        let mut video = GVVideo::load(&mut reader).unwrap();
         let frame = video.read_frame(1);
         assert!(frame.is_err());
-        assert_eq!(frame.err(), Some("End of video"));
+        assert!(matches!(frame, Err(GVError::FrameOutOfRange { id: 1, count: 1 })));
     }
 
     #[test]
     fn check_alpha() {
         let data = TEST_ALPHA_GV;
         let mut reader = Cursor::new(data);
-        let mut video = GVVideo::load(&mut reader);
+        let mut video = GVVideo::load(&mut reader).unwrap();
         let frame = video.read_frame(0).unwrap();
 
         // rgba: 189, 190, 189, 255
@@ -613,7 +701,7 @@ mod tests {
     fn read_frame_at() {
         let data = TEST_GV;
         let mut reader = Cursor::new(data);
-        let mut video = GVVideo::load(&mut reader);
+        let mut video = GVVideo::load(&mut reader).unwrap();
         let frame = video.read_frame_at(std::time::Duration::from_secs_f32(0.0)).unwrap();
         assert_eq!(frame.len(), 640 * 360);
     }
@@ -622,25 +710,25 @@ mod tests {
     fn read_frame_at_with_error() {
         let data = TEST_GV;
         let mut reader = Cursor::new(data);
-        let mut video = GVVideo::load(&mut reader);
+        let mut video = GVVideo::load(&mut reader).unwrap();
         let frame = video.read_frame_at(std::time::Duration::from_secs_f32(1.0));
         assert!(frame.is_err());
-        assert_eq!(frame.err(), Some("End of video"));
+        assert!(matches!(frame, Err(GVError::FrameOutOfRange { id: 30, count: 1 })));
     }
 
     #[test]
     fn check_duration1() {
         let data = TEST_GV;
         let mut reader = Cursor::new(data);
-        let video = GVVideo::load(&mut reader);
-        assert_eq!(video.get_duration(), std::time::Duration::from_secs_f32(1.0 / 30.0));
+        let video = GVVideo::load(&mut reader).unwrap();
+        assert_eq!(video.get_duration(), std::time::Duration::from_secs_f64(1.0 / 30.0));
     }
 
     #[test]
     fn check_duration2() {
         let data = TEST_10PX_GV;
         let mut reader = Cursor::new(data);
-        let video = GVVideo::load(&mut reader);
+        let video = GVVideo::load(&mut reader).unwrap();
         assert_eq!(video.get_duration(), std::time::Duration::from_secs_f32(5.0));
     }
 
@@ -648,7 +736,7 @@ mod tests {
     fn read_frame_at_3_5() {
         let data = TEST_10PX_GV;
         let mut reader = Cursor::new(data);
-        let mut video = GVVideo::load(&mut reader);
+        let mut video = GVVideo::load(&mut reader).unwrap();
         assert_eq!(video.header.width, 10);
         assert_eq!(video.header.height, 10);
         assert_eq!(video.header.frame_count, 5);
@@ -667,7 +755,118 @@ mod tests {
         // 5.01 sec is out of range
         let frame = video.read_frame_at(std::time::Duration::from_secs_f32(5.01));
         assert!(frame.is_err());
-        assert_eq!(frame.err(), Some("End of video"));
+        assert!(matches!(frame, Err(GVError::FrameOutOfRange { id: 5, count: 5 })));
+    }
+
+    #[test]
+    fn unknown_format() {
+        let mut data = TEST_GV.to_vec();
+        data[16] = 2;
+        let video = GVVideo::load(Cursor::new(data));
+        assert!(matches!(video, Err(GVError::UnknownFormat(2))));
+    }
+
+    #[test]
+    fn truncated_file() {
+        let video = GVVideo::load(Cursor::new(&TEST_GV[..12]));
+        assert!(matches!(video, Err(GVError::Truncated)));
+
+        // header says 5 frames, but address table is cut off
+        let video = GVVideo::load(Cursor::new(&TEST_10PX_GV[..40]));
+        assert!(matches!(video, Err(GVError::Truncated)));
+
+        // huge frame count is rejected before the address table is allocated
+        let mut data = TEST_10PX_GV.to_vec();
+        data[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+        let video = GVVideo::load_with_options(Cursor::new(data), GVLoadOptions::unlimited());
+        assert!(matches!(video, Err(GVError::Truncated)));
+
+        // lz4 block beyond the end of file is rejected before it is allocated
+        let mut video = GVVideo::load_with_options(Cursor::new(TEST_10PX_GV), GVLoadOptions::unlimited()).unwrap();
+        video.address_size_blocks[0].size = u64::MAX / 2;
+        assert!(matches!(video.read_frame(0), Err(GVError::Truncated)));
+        video.address_size_blocks[0].address = u64::MAX;
+        assert!(matches!(video.read_frame(0), Err(GVError::Truncated)));
+    }
+
+    #[test]
+    fn corrupt_lz4_block() {
+        let mut data = TEST_10PX_GV.to_vec();
+        // destroy the first frame
+        data[24..40].fill(0xFF);
+        let mut video = GVVideo::load(Cursor::new(data)).unwrap();
+        assert!(matches!(video.read_frame(0), Err(GVError::Lz4(_))));
+        assert!(matches!(video.read_frame_compressed(0), Err(GVError::Lz4(_))));
+        assert!(video.read_frame(1).is_ok());
+    }
+
+    #[test]
+    fn read_frame_into_buffer() {
+        let mut video = GVVideo::load(Cursor::new(TEST_ALPHA_GV)).unwrap();
+        let mut buffer = vec![0; 640 * 360];
+        video.read_frame_into(0, &mut buffer).unwrap();
+        assert_eq!(buffer, video.read_frame(0).unwrap());
+
+        let mut compressed = vec![0; 640 * 360 * 4];
+        let len = video.read_frame_compressed_into(0, &mut compressed).unwrap();
+        assert_eq!(len, video.get_frame_bytes() as usize);
+        assert_eq!(compressed[..len], video.read_frame_compressed(0).unwrap());
+
+        let mut small = vec![0; 640 * 360 - 1];
+        let res = video.read_frame_into(0, &mut small);
+        assert!(matches!(res, Err(GVError::InvalidFrameSize { expected: 230400, actual: 230399 })));
+        let res = video.read_frame_into(1, &mut buffer);
+        assert!(matches!(res, Err(GVError::FrameOutOfRange { id: 1, count: 1 })));
+    }
+
+    #[test]
+    fn frame_bytes_of_every_format() {
+        for format in [GVFormat::DXT1, GVFormat::DXT3, GVFormat::DXT5, GVFormat::BC7] {
+            for (width, height) in [(10, 10), (1, 1), (7, 3), (16, 8)] {
+                let frame_bytes = format.frame_bytes(width, height);
+                let data: Vec<u8> = (0..frame_bytes).map(|i| (i % 13) as u8).collect();
+                let mut writer = GVWriter::new(Cursor::new(Vec::new()), width, height, 30.0, format).unwrap();
+                writer.write_frame(&data).unwrap();
+                let file = writer.finish().unwrap().into_inner();
+
+                let mut video = GVVideo::load(Cursor::new(file.clone())).unwrap();
+                assert_eq!(video.header.expected_frame_bytes(), frame_bytes);
+                assert_eq!(video.read_frame_compressed(0).unwrap(), data, "{:?} {}x{}", format, width, height);
+                assert_eq!(video.read_frame_compressed_shared(0).unwrap(), data);
+                assert_eq!(video.read_frame(0).unwrap().len(), (width * height) as usize);
+                let shared = SharedGVVideo::load(file).unwrap();
+                assert_eq!(shared.read_frame_compressed(0).unwrap(), data);
+            }
+        }
+
+        // DXT1 frames are half of width * height * 4
+        let mut video = GVVideo::load(Cursor::new(TEST_10PX_GV)).unwrap();
+        assert_eq!(video.read_frame_compressed(0).unwrap().len(), 72);
+        let mut buffer = vec![0; 71];
+        assert!(matches!(
+            video.read_frame_compressed_into(0, &mut buffer),
+            Err(GVError::InvalidFrameSize { expected: 72, actual: 71 })
+        ));
+    }
+
+    #[test]
+    fn lz4_size_mismatch() {
+        // header says 20x10, lz4 blocks hold 10x10 frames
+        let mut data = TEST_10PX_GV.to_vec();
+        data[0] = 20;
+        let mut video = GVVideo::load(Cursor::new(data)).unwrap();
+        assert!(matches!(video.read_frame(0), Err(GVError::Lz4SizeMismatch { id: 0, expected: 120, actual: 72 })));
+        assert!(matches!(video.read_frame_compressed(1), Err(GVError::Lz4SizeMismatch { id: 1, expected: 120, actual: 72 })));
+
+        // header says 4x4, output is longer than one block
+        let mut data = TEST_10PX_GV.to_vec();
+        data[0] = 4;
+        data[4] = 4;
+        let mut video = GVVideo::load(Cursor::new(data)).unwrap();
+        match video.read_frame_compressed(0) {
+            Err(GVError::Lz4SizeMismatch { id: 0, expected: 8, actual }) => assert!(actual > 8),
+            other => panic!("unexpected {:?}", other),
+        }
     }
 
     #[test]
@@ -0,0 +1,131 @@
+// limits for loading untrusted files
+//
+// allocation sizes (frame buffers, lz4 input) come from header fields and the address table, so a crafted file
+// could otherwise request gigabytes. limits are checked in `load` (header) and on every frame read (lz4 block size).
+
+use std::io::{Read, Seek};
+
+use crate::{GVAddressSizeBlock, GVError, GVHeader, GVVideo};
+
+/// limits checked by `GVVideo::load_with_options` and frame reads
+///
+/// defaults accept any practical video (up to 16384x16384, 2^24 frames, 512 MiB lz4 block)
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct GVLoadOptions {
+    pub max_width: u32,
+    pub max_height: u32,
+    pub max_frame_count: u32,
+    /// max lz4 compressed size of one frame
+    pub max_frame_size: u64,
+}
+
+impl Default for GVLoadOptions {
+    fn default() -> Self {
+        GVLoadOptions {
+            max_width: 16384,
+            max_height: 16384,
+            max_frame_count: 1 << 24,
+            max_frame_size: 512 * 1024 * 1024,
+        }
+    }
+}
+
+fn check(what: &'static str, value: u64, limit: u64) -> Result<(), GVError> {
+    if value > limit {
+        return Err(GVError::LimitExceeded { what, value, limit });
+    }
+    Ok(())
+}
+
+impl GVLoadOptions {
+    /// no limits, for trusted files
+    pub fn unlimited() -> GVLoadOptions {
+        GVLoadOptions {
+            max_width: u32::MAX,
+            max_height: u32::MAX,
+            max_frame_count: u32::MAX,
+            max_frame_size: u64::MAX,
+        }
+    }
+
+    pub(crate) fn check_header(&self, header: &GVHeader) -> Result<(), GVError> {
+        check("width", header.width as u64, self.max_width as u64)?;
+        check("height", header.height as u64, self.max_height as u64)?;
+        check("frame count", header.frame_count as u64, self.max_frame_count as u64)
+    }
+
+    /// lz4 block size as usize, checked against `max_frame_size`
+    pub(crate) fn check_block(&self, block: &GVAddressSizeBlock) -> Result<usize, GVError> {
+        check("frame size", block.size, self.max_frame_size)?;
+        usize::try_from(block.size).map_err(|_| GVError::Truncated)
+    }
+}
+
+impl<Reader: Read + Seek> GVVideo<Reader> {
+    pub fn load_options(&self) -> &GVLoadOptions {
+        &self.options
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{write_header, GVFormat, SharedGVVideo};
+    use std::io::Cursor;
+
+    // 10px 5sec 1fps
+    const TEST_10PX_GV: &[u8; 474] = include_bytes!("../test_asset/test-10px.gv");
+
+    fn header_only(width: u32, height: u32, frame_count: u32) -> Vec<u8> {
+        let header = GVHeader { width, height, frame_count, fps: 30.0, format: GVFormat::DXT1, frame_bytes: 8 };
+        let mut data = Vec::new();
+        write_header(&mut data, &header).unwrap();
+        data
+    }
+
+    fn limit_exceeded<T: std::fmt::Debug>(result: Result<T, GVError>) -> &'static str {
+        match result {
+            Err(GVError::LimitExceeded { what, .. }) => what,
+            other => panic!("expected LimitExceeded, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn header_limits() {
+        let options = GVLoadOptions { max_width: 8, max_height: 8, max_frame_count: 4, max_frame_size: 1024 };
+        let load = |data: Vec<u8>| GVVideo::load_with_options(Cursor::new(data), options);
+        assert_eq!(limit_exceeded(load(header_only(9, 4, 0))), "width");
+        assert_eq!(limit_exceeded(load(header_only(4, 100000, 0))), "height");
+        assert_eq!(limit_exceeded(load(header_only(4, 4, u32::MAX))), "frame count");
+        assert!(load(header_only(8, 8, 0)).is_ok());
+
+        // default limits reject absurd dimensions before anything is allocated
+        let result = GVVideo::load(Cursor::new(header_only(u32::MAX, u32::MAX, 1)));
+        assert_eq!(limit_exceeded(result), "width");
+        // huge frame count with a small file is truncated
+        let result = GVVideo::load_with_options(Cursor::new(header_only(4, 4, u32::MAX)), GVLoadOptions::unlimited());
+        assert!(matches!(result, Err(GVError::Truncated)));
+    }
+
+    #[test]
+    fn frame_size_limit() {
+        let options = GVLoadOptions { max_frame_size: 73, ..GVLoadOptions::default() };
+        let mut video = GVVideo::load_with_options(Cursor::new(&TEST_10PX_GV[..]), options).unwrap();
+        assert_eq!(video.load_options(), &options);
+        // lz4 blocks of test-10px.gv are 74 bytes
+        assert_eq!(limit_exceeded(video.read_frame(0)), "frame size");
+        assert_eq!(limit_exceeded(video.read_frame_compressed(0)), "frame size");
+        assert_eq!(limit_exceeded(video.preload()), "frame size");
+        assert_eq!(limit_exceeded(video.frame_lz4(0)), "frame size");
+
+        let shared = SharedGVVideo::load_with_options(TEST_10PX_GV.to_vec(), options).unwrap();
+        assert_eq!(limit_exceeded(shared.read_frame(0)), "frame size");
+
+        // corrupt address table
+        let mut video = GVVideo::load(Cursor::new(&TEST_10PX_GV[..])).unwrap();
+        video.address_size_blocks[0].size = u64::MAX;
+        assert_eq!(limit_exceeded(video.read_frame(0)), "frame size");
+        video.address_size_blocks[0].size = 1 << 20;
+        assert!(matches!(video.read_frame(0), Err(GVError::Truncated)));
+    }
+}
@@ -0,0 +1,131 @@
+// zero-copy frame access for videos held in memory (memory-mapped file, `Vec<u8>`, `&[u8]`, ...)
+//
+// lz4 input is borrowed directly from the bytes using `address_size_blocks`, so these methods take `&self`
+// and frames can be read from several threads at once without seeking.
+
+use std::io::Cursor;
+
+use crate::{decode_bc, decompress_frame, GVError, GVVideo};
+
+#[cfg(feature = "mmap")]
+impl GVVideo<Cursor<memmap2::Mmap>> {
+    /// memory-map file, then read frames with `frame_lz4`, `read_frame_compressed_shared`, `read_frame_shared` (`&self`)
+    ///
+    /// the file must not be modified or truncated while it is mapped
+    pub fn open_mmap(file_path: impl AsRef<std::path::Path>) -> Result<GVVideo<Cursor<memmap2::Mmap>>, GVError> {
+        let file = std::fs::File::open(file_path)?;
+        // safety: the mapping is read-only, and the file is required not to be modified while mapped (see above)
+        let mmap = unsafe { memmap2::Mmap::map(&file)? };
+        GVVideo::load(Cursor::new(mmap))
+    }
+}
+
+impl<Bytes: AsRef<[u8]>> GVVideo<Cursor<Bytes>> {
+    /// raw lz4 block of the frame, borrowed from the underlying bytes
+    pub fn frame_lz4(&self, frame_id: u32) -> Result<&[u8], GVError> {
+        if frame_id >= self.header.frame_count {
+            return Err(GVError::FrameOutOfRange { id: frame_id, count: self.header.frame_count });
+        }
+
+        let block = self.address_size_blocks[frame_id as usize];
+        let bytes = self.reader.get_ref().as_ref();
+        let size = self.options.check_block(&block)?;
+        let start = usize::try_from(block.address).map_err(|_| GVError::Truncated)?;
+        let end = start.checked_add(size).ok_or(GVError::Truncated)?;
+        bytes.get(start..end).ok_or(GVError::Truncated)
+    }
+
+    /// decompress lz4 block into `buffer`, then return its length (compressed frame data, BC1, BC2, BC3, BC7)
+    ///
+    /// same as `read_frame_compressed_into`, but takes `&self` and does not copy lz4 input
+    pub fn read_frame_compressed_shared_into(&self, frame_id: u32, buffer: &mut [u8]) -> Result<usize, GVError> {
+        let lz4 = self.frame_lz4(frame_id)?;
+        decompress_frame(frame_id, lz4, buffer, self.uncompressed_size())
+    }
+
+    /// decompress lz4 block, then return compressed frame data (BC1, BC2, BC3, BC7)
+    pub fn read_frame_compressed_shared(&self, frame_id: u32) -> Result<Vec<u8>, GVError> {
+        let mut result = vec![0; self.uncompressed_size()];
+        self.read_frame_compressed_shared_into(frame_id, &mut result)?;
+        Ok(result)
+    }
+
+    /// decompress lz4 block and decode dxt into `buffer` (BGRA u32, at least width * height)
+    ///
+    /// same as `read_frame_into`, but takes `&self`, so the intermediate BC data is allocated on every call
+    pub fn read_frame_shared_into(&self, frame_id: u32, buffer: &mut [u32]) -> Result<(), GVError> {
+        let width = self.header.width as usize;
+        let height = self.header.height as usize;
+        if buffer.len() < width * height {
+            return Err(GVError::InvalidFrameSize { expected: width * height, actual: buffer.len() });
+        }
+
+        let data = self.read_frame_compressed_shared(frame_id)?;
+        decode_bc(&self.header, &data, buffer)
+    }
+
+    /// decompress lz4 block and decode dxt, then return decompressed frame data (BGRA u32)
+    pub fn read_frame_shared(&self, frame_id: u32) -> Result<Vec<u32>, GVError> {
+        let width = self.header.width as usize;
+        let height = self.header.height as usize;
+        let mut result = vec![0; width * height];
+        self.read_frame_shared_into(frame_id, &mut result)?;
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_ALPHA_GV: &[u8; 4857] = include_bytes!("../test_asset/test-alpha.gv");
+    // 10px 5sec 1fps
+    const TEST_10PX_GV: &[u8; 474] = include_bytes!("../test_asset/test-10px.gv");
+
+    #[test]
+    fn borrowed_lz4_block() {
+        let video = GVVideo::load(Cursor::new(&TEST_10PX_GV[..])).unwrap();
+        let block = video.address_size_blocks[2];
+        let lz4 = video.frame_lz4(2).unwrap();
+        assert_eq!(lz4.as_ptr(), TEST_10PX_GV[block.address as usize..].as_ptr());
+        assert_eq!(lz4.len(), block.size as usize);
+        assert!(matches!(video.frame_lz4(5), Err(GVError::FrameOutOfRange { id: 5, count: 5 })));
+    }
+
+    #[test]
+    fn same_as_read_frame() {
+        let mut video = GVVideo::load(Cursor::new(TEST_ALPHA_GV.to_vec())).unwrap();
+        for i in 0..video.get_frame_count() {
+            assert_eq!(video.read_frame_compressed_shared(i).unwrap(), video.read_frame_compressed(i).unwrap());
+            assert_eq!(video.read_frame_shared(i).unwrap(), video.read_frame(i).unwrap());
+        }
+    }
+
+    #[test]
+    fn block_outside_of_data() {
+        let mut video = GVVideo::load(Cursor::new(&TEST_10PX_GV[..])).unwrap();
+        video.address_size_blocks[1].address = TEST_10PX_GV.len() as u64 - 1;
+        assert!(matches!(video.frame_lz4(1), Err(GVError::Truncated)));
+        video.address_size_blocks[1].address = u64::MAX;
+        assert!(matches!(video.read_frame_shared(1), Err(GVError::Truncated)));
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn mmap_concurrent_reads() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/test_asset/test-10px.gv");
+        let video = GVVideo::open_mmap(path).unwrap();
+        let mut expected = GVVideo::load(Cursor::new(&TEST_10PX_GV[..])).unwrap();
+        let expected: Vec<Vec<u32>> = (0..5).map(|i| expected.read_frame(i).unwrap()).collect();
+
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for (i, frame) in expected.iter().enumerate() {
+                        assert_eq!(&video.read_frame_shared(i as u32).unwrap(), frame);
+                    }
+                });
+            }
+        });
+    }
+}
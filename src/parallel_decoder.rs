@@ -0,0 +1,83 @@
+// parallel BC decoding (rayon feature)
+//
+// frame is split into bands of block rows, and each band is decoded by the serial decoder
+// as an image of its own. bands start at block boundaries, so the result is identical to the serial path.
+
+use rayon::prelude::*;
+
+use crate::{decode_dxt_into, GVError, GVFormat};
+
+/// block rows per band
+const BAND_BLOCK_ROWS: usize = 4;
+
+/// decode BC1, BC2, BC3, BC7 data into BGRA u32 buffer (at least width * height) on rayon thread pool
+pub(crate) fn decode_dxt_parallel(data: &[u8], width: usize, height: usize, format: GVFormat, result: &mut [u32]) -> Result<(), GVError> {
+    if width == 0 || height == 0 {
+        return decode_dxt_into(data, width, height, format, result);
+    }
+
+    // same checks as the serial decoders, before data is sliced into bands
+    let band_bytes = width.div_ceil(4) * BAND_BLOCK_ROWS * format.block_bytes();
+    if data.len() < format.frame_bytes(width as u32, height as u32) {
+        return Err(GVError::BlockDecode { format, reason: "Not enough data to decode image!" });
+    }
+    if result.len() < width * height {
+        return Err(GVError::BlockDecode { format, reason: "Image buffer is too small!" });
+    }
+
+    let band_pixels = width * 4 * BAND_BLOCK_ROWS;
+    result[..width * height]
+        .par_chunks_mut(band_pixels)
+        .enumerate()
+        .try_for_each(|(band, image)| {
+            let band_height = image.len() / width;
+            let band_data = &data[band * band_bytes..];
+            decode_dxt_into(band_data, width, band_height, format, image)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{encode_bc2, encode_bc7, BC7Options, BC7Quality, BCQuality, GVVideo};
+    use std::io::Cursor;
+
+    const TEST_GV: &[u8; 1547] = include_bytes!("../test_asset/test.gv");
+    const TEST_ALPHA_GV: &[u8; 4857] = include_bytes!("../test_asset/test-alpha.gv");
+
+    fn assert_identical(data: &[u8], width: usize, height: usize, format: GVFormat) {
+        let mut serial = vec![0; width * height];
+        let mut parallel = vec![0; width * height];
+        decode_dxt_into(data, width, height, format, &mut serial).unwrap();
+        decode_dxt_parallel(data, width, height, format, &mut parallel).unwrap();
+        assert_eq!(serial, parallel, "{:?} {}x{}", format, width, height);
+    }
+
+    #[test]
+    fn identical_to_serial() {
+        let mut video = GVVideo::load(Cursor::new(TEST_GV)).unwrap();
+        let data = video.read_frame_compressed(0).unwrap();
+        assert_identical(&data, 640, 360, GVFormat::DXT1);
+
+        let mut video = GVVideo::load(Cursor::new(TEST_ALPHA_GV)).unwrap();
+        let data = video.read_frame_compressed(0).unwrap();
+        assert_identical(&data, 640, 360, GVFormat::DXT5);
+
+        // odd sizes, band boundaries not aligned with the frame height
+        let frame = video.read_frame(0).unwrap();
+        for (w, h) in [(37, 23), (5, 70), (13, 1)] {
+            let cropped: Vec<u32> = (0..w * h).map(|i| frame[(i % w) * 3 + (i / w) * 640 * 5]).collect();
+            let data = encode_bc2(&cropped, w, h, BCQuality::Fast, false).unwrap();
+            assert_identical(&data, w, h, GVFormat::DXT3);
+            let data = encode_bc7(&cropped, w, h, &BC7Options::new(BC7Quality::Fast)).unwrap();
+            assert_identical(&data, w, h, GVFormat::BC7);
+        }
+    }
+
+    #[test]
+    fn not_enough_data() {
+        let mut result = vec![0; 16 * 16];
+        let res = decode_dxt_parallel(&[0; 127], 16, 16, GVFormat::DXT1, &mut result);
+        assert!(matches!(res, Err(GVError::BlockDecode { format: GVFormat::DXT1, .. })));
+    }
+}
@@ -0,0 +1,277 @@
+// playback controller: which frame to show at a given time
+//
+// the player does not own the video, it only maps wall-clock instants to frame ids, e.g.
+//   let frame_id = player.frame_for(Instant::now());
+//   video.read_frame_compressed_into(frame_id, &mut buffer)?;
+//
+// playback position is kept in frames (unwrapped, can be negative or beyond the end), anchored at the
+// instant of the last state change, and wrapped by the loop mode when a frame id is requested.
+
+use std::{
+    io::{Read, Seek},
+    time::{Duration, Instant},
+};
+
+use crate::{clock::seconds_between, timing::FRAME_EPSILON, FrameRate, GVVideo};
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum LoopMode {
+    /// stop at the last frame (or the first frame when playing backward)
+    Once,
+    #[default]
+    Loop,
+    /// play forward, then backward, then forward ...
+    PingPong,
+}
+
+#[derive(Debug, Clone)]
+pub struct GVPlayer {
+    frame_count: u32,
+    fps: f64,
+    loop_mode: LoopMode,
+    rate: f64,
+    playing: bool,
+    /// position in frames at `anchor`
+    position: f64,
+    anchor: Instant,
+}
+
+impl GVPlayer {
+    /// paused at frame 0, rate 1.0, looping (stays at frame 0 when fps is not positive)
+    ///
+    /// NTSC rates such as 29.97 are played at their exact rational rate, see `FrameRate::from_fps`
+    pub fn new(frame_count: u32, fps: f32, now: Instant) -> GVPlayer {
+        GVPlayer {
+            frame_count,
+            fps: FrameRate::from_fps(fps).as_f64(),
+            loop_mode: LoopMode::default(),
+            rate: 1.0,
+            playing: false,
+            position: 0.0,
+            anchor: now,
+        }
+    }
+
+    pub fn for_video<Reader: Read + Seek>(video: &GVVideo<Reader>, now: Instant) -> GVPlayer {
+        GVPlayer::new(video.header.frame_count, video.header.fps, now)
+    }
+
+    /// unwrapped position in frames at `now`
+    fn raw_position(&self, now: Instant) -> f64 {
+        if !self.playing || self.fps <= 0.0 {
+            return self.position;
+        }
+        self.position + seconds_between(self.anchor, now) * self.rate * self.fps
+    }
+
+    /// start counting from the position at `now`
+    fn reanchor(&mut self, now: Instant) {
+        self.position = self.raw_position(now);
+        self.anchor = now;
+    }
+
+    /// wrap unwrapped frame index by loop mode
+    pub(crate) fn wrap(&self, frame: i64) -> u32 {
+        let count = self.frame_count as i64;
+        if count <= 1 {
+            return 0;
+        }
+        let frame = match self.loop_mode {
+            LoopMode::Once => frame.clamp(0, count - 1),
+            LoopMode::Loop => frame.rem_euclid(count),
+            LoopMode::PingPong => {
+                let period = count * 2 - 2;
+                let phase = frame.rem_euclid(period);
+                if phase < count { phase } else { period - phase }
+            }
+        };
+        frame as u32
+    }
+
+    /// unwrapped frame index at `now`, before the loop mode is applied
+    pub(crate) fn raw_frame(&self, now: Instant) -> i64 {
+        // saturating cast, far out of range positions are wrapped like any other
+        (self.raw_position(now) + FRAME_EPSILON).floor() as i64
+    }
+
+    /// frame id to show at `now`
+    pub fn frame_for(&self, now: Instant) -> u32 {
+        self.wrap(self.raw_frame(now))
+    }
+
+    /// presentation time of the frame shown at `now`
+    pub fn position(&self, now: Instant) -> Duration {
+        if self.fps <= 0.0 {
+            return Duration::ZERO;
+        }
+        Duration::try_from_secs_f64(self.frame_for(now) as f64 / self.fps).unwrap_or_default()
+    }
+
+    /// `LoopMode::Once` reached the end (or the beginning when playing backward)
+    pub fn is_finished(&self, now: Instant) -> bool {
+        if self.loop_mode != LoopMode::Once || !self.playing {
+            return false;
+        }
+        let position = self.raw_position(now);
+        (self.rate > 0.0 && position >= self.frame_count as f64) || (self.rate < 0.0 && position < 0.0)
+    }
+
+    pub fn play(&mut self, now: Instant) {
+        if !self.playing {
+            self.anchor = now;
+            self.playing = true;
+        }
+    }
+
+    /// freeze at the position of `now`
+    pub fn pause(&mut self, now: Instant) {
+        if self.playing {
+            self.reanchor(now);
+            self.playing = false;
+        }
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// playback speed, negative plays backward
+    pub fn set_rate(&mut self, rate: f64, now: Instant) {
+        self.reanchor(now);
+        self.rate = rate;
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    /// playback continues from the frame shown at `now`
+    pub fn set_loop_mode(&mut self, loop_mode: LoopMode, now: Instant) {
+        let frame = self.frame_for(now);
+        let fraction = self.raw_position(now).rem_euclid(1.0);
+        self.loop_mode = loop_mode;
+        self.position = frame as f64 + fraction;
+        self.anchor = now;
+    }
+
+    pub fn loop_mode(&self) -> LoopMode {
+        self.loop_mode
+    }
+
+    /// jump to frame (clamped to the last frame)
+    pub fn seek(&mut self, frame_id: u32, now: Instant) {
+        self.position = frame_id.min(self.frame_count.saturating_sub(1)) as f64;
+        self.anchor = now;
+    }
+
+    /// jump to time (clamped to the last frame)
+    pub fn seek_time(&mut self, time: Duration, now: Instant) {
+        let frame = (time.as_secs_f64() * self.fps).max(0.0);
+        self.position = frame.min(self.frame_count.saturating_sub(1) as f64);
+        self.anchor = now;
+    }
+
+    pub fn get_frame_count(&self) -> u32 {
+        self.frame_count
+    }
+
+    pub fn get_fps(&self) -> f32 {
+        self.fps as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// mock clock: instants at fixed offsets from an arbitrary start
+    struct MockClock(Instant);
+
+    impl MockClock {
+        fn at(&self, secs: f64) -> Instant {
+            self.0 + Duration::from_secs_f64(secs)
+        }
+    }
+
+    fn setup(frame_count: u32, fps: f32) -> (MockClock, GVPlayer) {
+        let clock = MockClock(Instant::now());
+        let player = GVPlayer::new(frame_count, fps, clock.at(0.0));
+        (clock, player)
+    }
+
+    #[test]
+    fn play_pause() {
+        let (clock, mut player) = setup(10, 2.0);
+        assert_eq!(player.frame_for(clock.at(3.0)), 0);
+
+        player.play(clock.at(1.0));
+        assert_eq!(player.frame_for(clock.at(1.0)), 0);
+        assert_eq!(player.frame_for(clock.at(1.5)), 1);
+        assert_eq!(player.frame_for(clock.at(2.99)), 3);
+        assert_eq!(player.frame_for(clock.at(3.0)), 4);
+
+        player.pause(clock.at(3.2));
+        assert!(!player.is_playing());
+        assert_eq!(player.frame_for(clock.at(100.0)), 4);
+        assert_eq!(player.position(clock.at(100.0)), Duration::from_secs(2));
+
+        player.play(clock.at(10.0));
+        assert_eq!(player.frame_for(clock.at(10.3)), 5);
+    }
+
+    #[test]
+    fn loop_modes() {
+        let (clock, mut player) = setup(4, 1.0);
+        player.play(clock.at(0.0));
+        let frames = |player: &GVPlayer| (0..12).map(|t| player.frame_for(clock.at(t as f64))).collect::<Vec<_>>();
+        assert_eq!(frames(&player), vec![0, 1, 2, 3, 0, 1, 2, 3, 0, 1, 2, 3]);
+
+        player.set_loop_mode(LoopMode::PingPong, clock.at(0.0));
+        assert_eq!(frames(&player), vec![0, 1, 2, 3, 2, 1, 0, 1, 2, 3, 2, 1]);
+
+        player.set_loop_mode(LoopMode::Once, clock.at(0.0));
+        assert_eq!(frames(&player), vec![0, 1, 2, 3, 3, 3, 3, 3, 3, 3, 3, 3]);
+        assert!(!player.is_finished(clock.at(3.5)));
+        assert!(player.is_finished(clock.at(4.0)));
+    }
+
+    #[test]
+    fn rate() {
+        let (clock, mut player) = setup(10, 10.0);
+        player.play(clock.at(0.0));
+        player.set_rate(2.0, clock.at(0.0));
+        assert_eq!(player.frame_for(clock.at(0.25)), 5);
+
+        // backward from frame 5, wrapping to the end
+        player.set_rate(-1.0, clock.at(0.25));
+        assert_eq!(player.frame_for(clock.at(0.45)), 3);
+        assert_eq!(player.frame_for(clock.at(0.8)), 9);
+
+        // backward once stops at the first frame
+        player.set_loop_mode(LoopMode::Once, clock.at(0.25));
+        assert_eq!(player.frame_for(clock.at(0.8)), 0);
+        assert!(player.is_finished(clock.at(0.8)));
+
+        // backward ping-pong bounces at the first frame
+        player.set_loop_mode(LoopMode::PingPong, clock.at(0.25));
+        assert_eq!(player.frame_for(clock.at(0.85)), 1);
+    }
+
+    #[test]
+    fn seek() {
+        let (clock, mut player) = setup(10, 10.0);
+        player.seek(7, clock.at(0.0));
+        assert_eq!(player.frame_for(clock.at(1.0)), 7);
+
+        player.play(clock.at(1.0));
+        player.seek_time(Duration::from_millis(200), clock.at(2.0));
+        assert_eq!(player.frame_for(clock.at(2.0)), 2);
+        assert_eq!(player.frame_for(clock.at(2.35)), 5);
+
+        player.seek(100, clock.at(3.0));
+        assert_eq!(player.frame_for(clock.at(3.0)), 9);
+
+        // instant before anchor
+        assert_eq!(player.frame_for(clock.at(2.9)), 8);
+    }
+}
@@ -0,0 +1,428 @@
+// background decoding for playback
+//
+// a worker thread owns the video and decodes the frames in a look-ahead window starting at the playhead. the
+// consumer takes frames with `get` / `try_get`, which moves the playhead and lets the worker refill the window.
+
+use std::{
+    collections::VecDeque,
+    io::{Read, Seek},
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    thread::JoinHandle,
+};
+
+use crate::{GVError, GVFrame, GVHeader, GVVideo};
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum PrefetchDirection {
+    #[default]
+    Forward,
+    Backward,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct GVPrefetchOptions {
+    /// number of frames decoded ahead of the playhead (including the playhead), 0 is treated as 1
+    pub depth: usize,
+    pub direction: PrefetchDirection,
+    /// wrap around at the end (or the beginning, when backward) of the video
+    pub looping: bool,
+}
+
+impl Default for GVPrefetchOptions {
+    fn default() -> Self {
+        GVPrefetchOptions { depth: 8, direction: PrefetchDirection::Forward, looping: false }
+    }
+}
+
+type ReadFn<Reader, T> = fn(&mut GVVideo<Reader>, u32) -> Result<T, GVError>;
+
+struct State<T> {
+    /// next frame expected by the consumer, may be outside of the video when not looping
+    playhead: i64,
+    options: GVPrefetchOptions,
+    frame_count: u32,
+    /// frames in the look-ahead window, at most `options.depth`
+    ring: VecDeque<(u32, Result<GVFrame<T>, GVError>)>,
+    stop: bool,
+    /// set when the worker unwinds, waiting for frames would block forever
+    panicked: bool,
+}
+
+impl<T> State<T> {
+    /// frame ids from the playhead in playback direction
+    fn window(&self) -> impl Iterator<Item = u32> + '_ {
+        let count = self.frame_count as i64;
+        let step = match self.options.direction {
+            PrefetchDirection::Forward => 1,
+            PrefetchDirection::Backward => -1,
+        };
+        let depth = if self.options.looping { self.options.depth.min(self.frame_count as usize) } else { self.options.depth };
+        (0..depth as i64)
+            .map(move |k| self.playhead + k * step)
+            .map(move |id| if self.options.looping && count > 0 { id.rem_euclid(count) } else { id })
+            .take_while(move |&id| (0..count).contains(&id))
+            .map(|id| id as u32)
+    }
+
+    fn in_window(&self, frame_id: u32) -> bool {
+        self.window().any(|id| id == frame_id)
+    }
+
+    fn next_missing(&self) -> Option<u32> {
+        self.window().find(|&id| !self.ring.iter().any(|(ready, _)| *ready == id))
+    }
+
+    /// drop frames which left the window
+    fn retain_window(&mut self) {
+        let window: Vec<u32> = self.window().collect();
+        self.ring.retain(|(id, _)| window.contains(id));
+    }
+
+    fn set_playhead(&mut self, frame_id: i64) {
+        self.playhead = frame_id;
+        self.retain_window();
+    }
+
+    fn advance(&mut self, frame_id: u32) {
+        let next = match self.options.direction {
+            PrefetchDirection::Forward => frame_id as i64 + 1,
+            PrefetchDirection::Backward => frame_id as i64 - 1,
+        };
+        self.set_playhead(next);
+    }
+}
+
+struct Shared<T> {
+    state: Mutex<State<T>>,
+    changed: Condvar,
+}
+
+impl<T> Shared<T> {
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// marks the state when the worker panics and wakes up waiting consumers
+struct PanicGuard<T>(Arc<Shared<T>>);
+
+impl<T> Drop for PanicGuard<T> {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            self.0.lock().panicked = true;
+            self.0.changed.notify_all();
+        }
+    }
+}
+
+/// decodes frames ahead of the playhead on a worker thread
+///
+/// create with `GVPrefetcher::decoded` (BGRA u32, same as `read_frame`) or `GVPrefetcher::compressed`
+/// (BC compressed, same as `read_frame_compressed`), then take frames with `get` / `try_get`.
+pub struct GVPrefetcher<Reader: Read + Seek + Send + 'static, T: Send + 'static> {
+    header: GVHeader,
+    shared: Arc<Shared<T>>,
+    worker: Option<JoinHandle<GVVideo<Reader>>>,
+}
+
+impl<Reader: Read + Seek + Send + 'static> GVPrefetcher<Reader, Vec<u32>> {
+    /// prefetch decompressed frames (BGRA u32)
+    pub fn decoded(video: GVVideo<Reader>, options: GVPrefetchOptions) -> Self {
+        GVPrefetcher::spawn(video, options, GVVideo::read_frame)
+    }
+}
+
+impl<Reader: Read + Seek + Send + 'static> GVPrefetcher<Reader, Vec<u8>> {
+    /// prefetch BC compressed frames
+    pub fn compressed(video: GVVideo<Reader>, options: GVPrefetchOptions) -> Self {
+        GVPrefetcher::spawn(video, options, GVVideo::read_frame_compressed)
+    }
+}
+
+impl<Reader: Read + Seek + Send + 'static, T: Send + 'static> GVPrefetcher<Reader, T> {
+    fn spawn(video: GVVideo<Reader>, mut options: GVPrefetchOptions, read: ReadFn<Reader, T>) -> Self {
+        // the window must contain at least the playhead, otherwise `get` waits forever
+        options.depth = options.depth.max(1);
+        let header = video.header.clone();
+        let playhead = match options.direction {
+            PrefetchDirection::Forward => 0,
+            PrefetchDirection::Backward => header.frame_count as i64 - 1,
+        };
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                playhead,
+                options,
+                frame_count: header.frame_count,
+                ring: VecDeque::with_capacity(options.depth),
+                stop: false,
+                panicked: false,
+            }),
+            changed: Condvar::new(),
+        });
+
+        let worker_shared = Arc::clone(&shared);
+        let worker = std::thread::spawn(move || worker(video, worker_shared, read));
+
+        GVPrefetcher { header, shared, worker: Some(worker) }
+    }
+
+    pub fn header(&self) -> &GVHeader {
+        &self.header
+    }
+
+    pub fn get_frame_count(&self) -> u32 {
+        self.header.frame_count
+    }
+
+    /// take frame, waiting for the worker if it is not ready yet, then move the playhead to the following frame
+    ///
+    /// returns `GVError::WorkerPanicked` instead of waiting when the worker thread has panicked
+    pub fn get(&self, frame_id: u32) -> Result<GVFrame<T>, GVError> {
+        if frame_id >= self.header.frame_count {
+            return Err(GVError::FrameOutOfRange { id: frame_id, count: self.header.frame_count });
+        }
+
+        let mut state = self.shared.lock();
+        if !state.in_window(frame_id) {
+            state.set_playhead(frame_id as i64);
+            self.shared.changed.notify_all();
+        }
+        loop {
+            if let Some(pos) = state.ring.iter().position(|(id, _)| *id == frame_id) {
+                let (_, frame) = state.ring.remove(pos).unwrap();
+                state.advance(frame_id);
+                self.shared.changed.notify_all();
+                return frame;
+            }
+            if state.panicked {
+                return Err(GVError::WorkerPanicked);
+            }
+            state = self.shared.changed.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    /// take frame if it is ready, otherwise move the playhead to it and return None
+    pub fn try_get(&self, frame_id: u32) -> Option<Result<GVFrame<T>, GVError>> {
+        if frame_id >= self.header.frame_count {
+            return Some(Err(GVError::FrameOutOfRange { id: frame_id, count: self.header.frame_count }));
+        }
+
+        let mut state = self.shared.lock();
+        match state.ring.iter().position(|(id, _)| *id == frame_id) {
+            Some(pos) => {
+                let (_, frame) = state.ring.remove(pos).unwrap();
+                state.advance(frame_id);
+                self.shared.changed.notify_all();
+                Some(frame)
+            }
+            None if state.panicked => Some(Err(GVError::WorkerPanicked)),
+            None => {
+                if !state.in_window(frame_id) {
+                    state.set_playhead(frame_id as i64);
+                    self.shared.changed.notify_all();
+                }
+                None
+            }
+        }
+    }
+
+    /// whether frame is decoded and waiting in the buffer
+    pub fn is_ready(&self, frame_id: u32) -> bool {
+        self.shared.lock().ring.iter().any(|(id, _)| *id == frame_id)
+    }
+
+    /// number of decoded frames waiting in the buffer
+    pub fn ready_count(&self) -> usize {
+        self.shared.lock().ring.len()
+    }
+
+    /// move the playhead, frames outside of the new look-ahead window are dropped
+    pub fn seek(&self, frame_id: u32) {
+        self.shared.lock().set_playhead(frame_id as i64);
+        self.shared.changed.notify_all();
+    }
+
+    pub fn set_direction(&self, direction: PrefetchDirection) {
+        let mut state = self.shared.lock();
+        state.options.direction = direction;
+        state.retain_window();
+        self.shared.changed.notify_all();
+    }
+
+    /// change the look-ahead window size, 0 is treated as 1
+    pub fn set_depth(&self, depth: usize) {
+        let mut state = self.shared.lock();
+        state.options.depth = depth.max(1);
+        state.retain_window();
+        self.shared.changed.notify_all();
+    }
+
+    pub fn options(&self) -> GVPrefetchOptions {
+        self.shared.lock().options
+    }
+
+    /// stop the worker and return the video, resumes the worker's panic if it panicked
+    pub fn into_inner(mut self) -> GVVideo<Reader> {
+        match self.stop() {
+            Some(Ok(video)) => video,
+            Some(Err(panic)) => std::panic::resume_unwind(panic),
+            None => unreachable!("worker is only taken by into_inner and drop"),
+        }
+    }
+
+    fn stop(&mut self) -> Option<std::thread::Result<GVVideo<Reader>>> {
+        let worker = self.worker.take()?;
+        self.shared.lock().stop = true;
+        self.shared.changed.notify_all();
+        Some(worker.join())
+    }
+}
+
+impl<Reader: Read + Seek + Send + 'static, T: Send + 'static> Drop for GVPrefetcher<Reader, T> {
+    fn drop(&mut self) {
+        // a worker panic was already reported by `get` / `try_get`
+        self.stop();
+    }
+}
+
+fn worker<Reader: Read + Seek, T>(mut video: GVVideo<Reader>, shared: Arc<Shared<T>>, read: ReadFn<Reader, T>) -> GVVideo<Reader> {
+    let _guard = PanicGuard(Arc::clone(&shared));
+    loop {
+        let frame_id = {
+            let mut state = shared.lock();
+            loop {
+                if state.stop {
+                    return video;
+                }
+                if let Some(frame_id) = state.next_missing() {
+                    break frame_id;
+                }
+                state = shared.changed.wait(state).unwrap_or_else(|e| e.into_inner());
+            }
+        };
+
+        let frame = read(&mut video, frame_id).map(|data| GVFrame {
+            id: frame_id,
+            timestamp: video.timestamp_of(frame_id),
+            data,
+        });
+
+        let mut state = shared.lock();
+        // playhead may have moved while reading
+        if state.in_window(frame_id) && !state.ring.iter().any(|(id, _)| *id == frame_id) {
+            state.ring.push_back((frame_id, frame));
+            shared.changed.notify_all();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::time::{Duration, Instant};
+
+    // 10px 5sec 1fps
+    const TEST_10PX_GV: &[u8; 474] = include_bytes!("../test_asset/test-10px.gv");
+
+    fn load() -> GVVideo<Cursor<&'static [u8]>> {
+        GVVideo::load(Cursor::new(&TEST_10PX_GV[..])).unwrap()
+    }
+
+    fn wait_until(condition: impl Fn() -> bool) {
+        let start = Instant::now();
+        while !condition() {
+            assert!(start.elapsed() < Duration::from_secs(5), "timeout");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn forward() {
+        let mut video = load();
+        let expected: Vec<Vec<u32>> = (0..5).map(|i| video.read_frame(i).unwrap()).collect();
+
+        let prefetcher = GVPrefetcher::decoded(video, GVPrefetchOptions { depth: 3, ..Default::default() });
+        wait_until(|| prefetcher.ready_count() == 3);
+        assert!(prefetcher.is_ready(2));
+        assert!(!prefetcher.is_ready(3));
+
+        for i in 0..5 {
+            let frame = prefetcher.get(i).unwrap();
+            assert_eq!(frame.id, i);
+            assert_eq!(frame.timestamp, Duration::from_secs(i as u64));
+            assert_eq!(frame.data, expected[i as usize]);
+        }
+        assert!(matches!(prefetcher.get(5), Err(GVError::FrameOutOfRange { id: 5, count: 5 })));
+
+        // video is returned
+        let mut video = prefetcher.into_inner();
+        assert_eq!(video.read_frame(0).unwrap(), expected[0]);
+    }
+
+    #[test]
+    fn backward_compressed() {
+        let mut video = load();
+        let expected = video.read_frame_compressed(4).unwrap();
+
+        let options = GVPrefetchOptions { depth: 2, direction: PrefetchDirection::Backward, looping: false };
+        let prefetcher = GVPrefetcher::compressed(video, options);
+        wait_until(|| prefetcher.is_ready(4) && prefetcher.is_ready(3));
+        assert_eq!(prefetcher.get(4).unwrap().data, expected);
+        wait_until(|| prefetcher.is_ready(2));
+        let ids: Vec<u32> = (0..4).rev().map(|i| prefetcher.get(i).unwrap().id).collect();
+        assert_eq!(ids, vec![3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn looping_and_seek() {
+        let options = GVPrefetchOptions { depth: 3, direction: PrefetchDirection::Forward, looping: true };
+        let prefetcher = GVPrefetcher::compressed(load(), options);
+
+        prefetcher.seek(4);
+        // 4, 0, 1 are in the window
+        wait_until(|| prefetcher.is_ready(4) && prefetcher.is_ready(0) && prefetcher.is_ready(1));
+        assert!(!prefetcher.is_ready(2));
+        assert_eq!(prefetcher.get(4).unwrap().id, 4);
+        assert_eq!(prefetcher.get(0).unwrap().id, 0);
+
+        // not ready yet after jumping, playhead moves there
+        prefetcher.set_direction(PrefetchDirection::Backward);
+        let frame = loop {
+            if let Some(frame) = prefetcher.try_get(3) {
+                break frame;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        };
+        assert_eq!(frame.unwrap().id, 3);
+        wait_until(|| prefetcher.is_ready(1));
+        assert_eq!(prefetcher.get(2).unwrap().id, 2);
+    }
+
+    #[test]
+    fn zero_depth() {
+        let options = GVPrefetchOptions { depth: 0, ..Default::default() };
+        let prefetcher = GVPrefetcher::compressed(load(), options);
+        assert_eq!(prefetcher.options().depth, 1);
+        assert_eq!(prefetcher.get(0).unwrap().id, 0);
+        assert_eq!(prefetcher.get(3).unwrap().id, 3);
+
+        prefetcher.set_depth(0);
+        assert_eq!(prefetcher.options().depth, 1);
+        assert_eq!(prefetcher.get(4).unwrap().id, 4);
+        assert_eq!(prefetcher.get(1).unwrap().id, 1);
+    }
+
+    #[test]
+    fn worker_panic() {
+        fn panicking(_: &mut GVVideo<Cursor<&'static [u8]>>, _: u32) -> Result<Vec<u8>, GVError> {
+            panic!("decoder bug");
+        }
+
+        let prefetcher = GVPrefetcher::spawn(load(), GVPrefetchOptions::default(), panicking);
+        assert!(matches!(prefetcher.get(0), Err(GVError::WorkerPanicked)));
+        assert!(matches!(prefetcher.try_get(1), Some(Err(GVError::WorkerPanicked))));
+        // dropping does not propagate the worker panic
+        drop(prefetcher);
+    }
+}
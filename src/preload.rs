@@ -0,0 +1,180 @@
+// on-memory mode: lz4 blocks are read once, then frames are served without I/O
+//
+// same as ofxExtremeGpuVideo's on-memory mode, but a subrange of frames can be preloaded too.
+
+use std::{
+    io::{Read, Seek, SeekFrom},
+    ops::{Range, RangeBounds},
+};
+
+use crate::{GVError, GVVideo};
+
+/// memory held by preloaded lz4 blocks, see `GVVideo::preload()`
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct GVMemoryUsage {
+    pub frame_count: u32,
+    /// total size of preloaded lz4 blocks
+    pub bytes: usize,
+}
+
+/// lz4 blocks of a range of frames, stored back to back
+pub(crate) struct Preloaded {
+    range: Range<u32>,
+    data: Vec<u8>,
+    /// start of each block in `data`, plus end of the last block
+    offsets: Vec<usize>,
+}
+
+impl Preloaded {
+    pub(crate) fn block(&self, frame_id: u32) -> Option<&[u8]> {
+        if !self.range.contains(&frame_id) {
+            return None;
+        }
+        let i = (frame_id - self.range.start) as usize;
+        Some(&self.data[self.offsets[i]..self.offsets[i + 1]])
+    }
+}
+
+impl std::fmt::Debug for Preloaded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Preloaded")
+            .field("range", &self.range)
+            .field("bytes", &self.data.len())
+            .finish()
+    }
+}
+
+impl<Reader: Read + Seek> GVVideo<Reader> {
+    /// load video, then preload all frames (see `preload()`)
+    pub fn load_into_memory(reader: Reader) -> Result<GVVideo<Reader>, GVError> {
+        let mut video = GVVideo::load(reader)?;
+        video.preload()?;
+        Ok(video)
+    }
+
+    /// read lz4 blocks of all frames into memory, `read_frame*` then does no I/O
+    pub fn preload(&mut self) -> Result<GVMemoryUsage, GVError> {
+        self.preload_range(..)
+    }
+
+    /// read lz4 blocks of frames in range (clamped to frame count) into memory, replacing previously preloaded frames
+    pub fn preload_range(&mut self, range: impl RangeBounds<u32>) -> Result<GVMemoryUsage, GVError> {
+        let range = self.frame_range(range);
+        let blocks = &self.address_size_blocks[range.start as usize..range.end as usize];
+
+        // release previous blocks first, so memory is not held twice
+        self.preloaded = None;
+
+        // corrupt address table must not cause huge allocation, every block must lie within the file
+        let mut sizes = Vec::with_capacity(blocks.len());
+        for block in blocks {
+            let size = self.options.check_block(block)?;
+            if block.address.checked_add(block.size).map_or(true, |end| end > self.file_size) {
+                return Err(GVError::Truncated);
+            }
+            sizes.push(size);
+        }
+        let total = sizes
+            .iter()
+            .try_fold(0usize, |total, &size| total.checked_add(size))
+            .filter(|&total| total as u64 <= self.file_size)
+            .ok_or(GVError::Truncated)?;
+        let mut data = Vec::new();
+        data.try_reserve_exact(total)
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::OutOfMemory))?;
+
+        let mut offsets = Vec::with_capacity(blocks.len() + 1);
+        offsets.push(0);
+        for (block, size) in blocks.iter().zip(sizes) {
+            let start = data.len();
+            data.resize(start + size, 0);
+            self.reader.seek(SeekFrom::Start(block.address))?;
+            self.reader.read_exact(&mut data[start..])?;
+            offsets.push(data.len());
+        }
+
+        self.preloaded = Some(Preloaded { range, data, offsets });
+        Ok(self.memory_usage())
+    }
+
+    /// release preloaded lz4 blocks, frames are read from the reader again
+    pub fn unload(&mut self) {
+        self.preloaded = None;
+    }
+
+    pub fn is_preloaded(&self, frame_id: u32) -> bool {
+        self.preloaded.as_ref().is_some_and(|p| p.range.contains(&frame_id))
+    }
+
+    /// memory held by preloaded lz4 blocks (zero when nothing is preloaded)
+    pub fn memory_usage(&self) -> GVMemoryUsage {
+        match &self.preloaded {
+            Some(p) => GVMemoryUsage { frame_count: p.range.len() as u32, bytes: p.data.len() },
+            None => GVMemoryUsage::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const TEST_ALPHA_GV: &[u8; 4857] = include_bytes!("../test_asset/test-alpha.gv");
+    // 10px 5sec 1fps
+    const TEST_10PX_GV: &[u8; 474] = include_bytes!("../test_asset/test-10px.gv");
+
+    #[test]
+    fn preload_all() {
+        let mut expected = GVVideo::load(Cursor::new(&TEST_ALPHA_GV[..])).unwrap();
+        let mut video = GVVideo::load_into_memory(Cursor::new(&TEST_ALPHA_GV[..])).unwrap();
+        let total: u64 = video.address_size_blocks.iter().map(|b| b.size).sum();
+        let usage = video.memory_usage();
+        assert_eq!(usage.frame_count, video.get_frame_count());
+        assert_eq!(usage.bytes, total as usize);
+
+        // no I/O after preload
+        video.reader = Cursor::new(&[]);
+        for i in 0..video.get_frame_count() {
+            assert_eq!(video.read_frame(i).unwrap(), expected.read_frame(i).unwrap());
+            assert_eq!(video.read_frame_compressed(i).unwrap(), expected.read_frame_compressed(i).unwrap());
+        }
+
+        video.unload();
+        assert_eq!(video.memory_usage(), GVMemoryUsage::default());
+        assert!(matches!(video.read_frame(0), Err(GVError::Truncated)));
+    }
+
+    #[test]
+    fn preload_subrange() {
+        let mut video = GVVideo::load(Cursor::new(&TEST_10PX_GV[..])).unwrap();
+        let expected = video.read_frame(2).unwrap();
+
+        let usage = video.preload_range(1..3).unwrap();
+        let blocks = &video.address_size_blocks;
+        assert_eq!(usage, GVMemoryUsage { frame_count: 2, bytes: (blocks[1].size + blocks[2].size) as usize });
+        assert!(!video.is_preloaded(0));
+        assert!(video.is_preloaded(1));
+        assert!(!video.is_preloaded(3));
+
+        video.reader = Cursor::new(&[]);
+        assert_eq!(video.read_frame(2).unwrap(), expected);
+        assert!(matches!(video.read_frame(3), Err(GVError::Truncated)));
+        assert!(matches!(video.read_frame(5), Err(GVError::FrameOutOfRange { id: 5, count: 5 })));
+
+        // clamped to frame count
+        video.reader = Cursor::new(&TEST_10PX_GV[..]);
+        assert_eq!(video.preload_range(3..100).unwrap().frame_count, 2);
+    }
+
+    #[test]
+    fn corrupt_table() {
+        let mut video = GVVideo::load(Cursor::new(&TEST_10PX_GV[..])).unwrap();
+        // small in total, but the block lies outside of the file
+        video.address_size_blocks[1].address = 470;
+        assert!(matches!(video.preload(), Err(GVError::Truncated)));
+        video.address_size_blocks[1].address = u64::MAX;
+        assert!(matches!(video.preload(), Err(GVError::Truncated)));
+        assert_eq!(video.memory_usage(), GVMemoryUsage::default());
+    }
+}
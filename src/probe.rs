@@ -0,0 +1,129 @@
+// lightweight inspection of GV files
+//
+// `GVVideo::load` reads the whole address table, which is slow when listing many files. `probe` reads only the
+// 24-byte header and the file length. GV has no magic number, so `is_probably_gv` sniffs files by checking that
+// the header is consistent with itself and with the file length.
+
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::{read_header, validate::min_file_size, GVError, GVHeader, GVIssue, GVVideo, HEADER_SIZE};
+
+/// header and file length of a GV file, see `GVVideo::probe`
+#[derive(Debug, PartialEq, Clone)]
+pub struct GVInfo {
+    pub header: GVHeader,
+    pub file_size: u64,
+    /// where the address table starts, None when the file is too small for it
+    pub table_offset: Option<u64>,
+    /// inconsistencies found without reading the address table
+    pub issues: Vec<GVIssue>,
+}
+
+impl GVInfo {
+    /// header looks valid and the file is large enough for the frames and the address table
+    pub fn is_plausible(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl<Reader: Read + Seek> GVVideo<Reader> {
+    /// read only the header and the file length, much cheaper than `load`
+    pub fn probe(mut reader: Reader) -> Result<GVInfo, GVError> {
+        reader.seek(SeekFrom::Start(0))?;
+        let header = read_header(&mut reader)?;
+        let file_size = reader.seek(SeekFrom::End(0))?;
+
+        let mut issues = Vec::new();
+        if header.width == 0 || header.height == 0 {
+            issues.push(GVIssue::EmptyResolution { width: header.width, height: header.height });
+        }
+        if !(header.fps > 0.0 && header.fps.is_finite()) {
+            issues.push(GVIssue::InvalidFps(header.fps));
+        }
+        let frame_bytes = header.expected_frame_bytes();
+        if header.frame_bytes as usize != frame_bytes {
+            issues.push(GVIssue::FrameBytesMismatch { stored: header.frame_bytes, expected: frame_bytes });
+        }
+        let min_size = min_file_size(&header);
+        if file_size < min_size {
+            issues.push(GVIssue::FileTooSmall { file_size, min_size });
+        }
+        let table_offset = file_size
+            .checked_sub(header.frame_count as u64 * 16)
+            .filter(|&offset| offset >= HEADER_SIZE as u64);
+
+        Ok(GVInfo { header, file_size, table_offset, issues })
+    }
+}
+
+/// whether `reader` looks like a GV file (header is consistent and fits the file length), reads only the header
+pub fn is_probably_gv<Reader: Read + Seek>(reader: Reader) -> bool {
+    GVVideo::probe(reader).is_ok_and(|info| info.is_plausible())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GVFormat;
+    use std::io::Cursor;
+
+    // 10px 5sec 1fps
+    const TEST_10PX_GV: &[u8; 474] = include_bytes!("../test_asset/test-10px.gv");
+    // SMPTE BAR
+    const TEST_GV: &[u8; 1547] = include_bytes!("../test_asset/test.gv");
+
+    /// counts bytes read, to check that probe does not read the address table
+    struct CountingReader<R> {
+        inner: R,
+        read: usize,
+    }
+
+    impl<R: Read> Read for CountingReader<R> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let len = self.inner.read(buf)?;
+            self.read += len;
+            Ok(len)
+        }
+    }
+
+    impl<R: Seek> Seek for CountingReader<R> {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    #[test]
+    fn probe_file() {
+        let mut reader = CountingReader { inner: Cursor::new(TEST_10PX_GV), read: 0 };
+        let info = GVVideo::probe(&mut reader).unwrap();
+        assert_eq!(reader.read, HEADER_SIZE);
+        assert_eq!(info.header.width, 10);
+        assert_eq!(info.header.frame_count, 5);
+        assert_eq!(info.header.format, GVFormat::DXT1);
+        assert_eq!(info.file_size, 474);
+        assert_eq!(info.table_offset, Some(474 - 5 * 16));
+        assert!(info.is_plausible());
+        assert_eq!(info.header, GVVideo::load(Cursor::new(TEST_10PX_GV)).unwrap().header);
+    }
+
+    #[test]
+    fn probe_truncated() {
+        let info = GVVideo::probe(Cursor::new(&TEST_10PX_GV[..100])).unwrap();
+        assert_eq!(info.table_offset, None);
+        assert_eq!(info.issues, vec![GVIssue::FileTooSmall { file_size: 100, min_size: 24 + 5 * 17 }]);
+        assert!(GVVideo::probe(Cursor::new(&TEST_10PX_GV[..10])).is_err());
+    }
+
+    #[test]
+    fn sniff() {
+        assert!(is_probably_gv(Cursor::new(TEST_10PX_GV)));
+        assert!(is_probably_gv(Cursor::new(TEST_GV)));
+        assert!(!is_probably_gv(Cursor::new(b"")));
+        assert!(!is_probably_gv(Cursor::new(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\0\0\0\x10\0\0\0\x10\x08\x06\0\0\0")));
+        assert!(!is_probably_gv(Cursor::new(b"hello world, this is not a gv video file")));
+        // valid format id but frame bytes do not match
+        let mut data = TEST_10PX_GV.to_vec();
+        data[20] = 73;
+        assert!(!is_probably_gv(Cursor::new(data)));
+    }
+}
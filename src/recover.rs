@@ -0,0 +1,185 @@
+// recovery of files without a valid address table
+//
+// a recorder which stops before `GVWriter::finish` leaves the header (with frame count 0) and the lz4 blocks, but
+// no address table. lz4 blocks have no length prefix, so each block is found by walking lz4 sequences from the end
+// of the previous one until exactly frame bytes have been produced.
+
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::{read_header, GVAddressSizeBlock, GVError, GVLoadOptions, GVVideo, Scratch, HEADER_SIZE};
+
+/// length of the lz4 block at the start of `data` which decompresses to exactly `output_len` bytes
+fn lz4_block_len(data: &[u8], output_len: usize) -> Option<usize> {
+    // lengths of 15 continue with extension bytes, added up until a byte other than 255
+    fn extension(data: &[u8], pos: &mut usize) -> Option<usize> {
+        let mut len = 0usize;
+        loop {
+            let byte = *data.get(*pos)?;
+            *pos += 1;
+            len = len.checked_add(byte as usize)?;
+            if byte != 255 {
+                return Some(len);
+            }
+        }
+    }
+
+    let mut pos = 0;
+    let mut out = 0usize;
+    loop {
+        let token = *data.get(pos)?;
+        pos += 1;
+        let mut literals = (token >> 4) as usize;
+        if literals == 15 {
+            literals += extension(data, &mut pos)?;
+        }
+        pos = pos.checked_add(literals).filter(|&pos| pos <= data.len())?;
+        out = out.checked_add(literals)?;
+        // the last sequence of a block has literals only
+        if out == output_len {
+            return Some(pos);
+        }
+
+        let offset = u16::from_le_bytes([*data.get(pos)?, *data.get(pos + 1)?]) as usize;
+        pos += 2;
+        if offset == 0 || offset > out {
+            return None;
+        }
+        let mut match_len = (token & 0xf) as usize + 4;
+        if token & 0xf == 15 {
+            match_len += extension(data, &mut pos)?;
+        }
+        out = out.checked_add(match_len).filter(|&out| out <= output_len)?;
+    }
+}
+
+impl<Reader: Read + Seek> GVVideo<Reader> {
+    /// rebuild the address table by scanning lz4 blocks after the header, with default `GVLoadOptions` limits
+    pub fn recover(reader: Reader) -> Result<GVVideo<Reader>, GVError> {
+        GVVideo::recover_with_options(reader, GVLoadOptions::default())
+    }
+
+    /// rebuild the address table by scanning lz4 blocks after the header, for files whose table is missing
+    ///
+    /// blocks must be stored back to back from the end of the header (as `GVWriter` does). scanning stops at the
+    /// first position which is not an lz4 block of frame bytes, so a partially written last frame is dropped.
+    /// header frame count and frame bytes are replaced by the recovered values.
+    pub fn recover_with_options(mut reader: Reader, options: GVLoadOptions) -> Result<GVVideo<Reader>, GVError> {
+        let mut header = read_header(&mut reader)?;
+        // frame count in the header is unknown (zero when the writer did not finish)
+        header.frame_count = 0;
+        options.check_header(&header)?;
+        let frame_bytes = header.expected_frame_bytes();
+        header.frame_bytes = u32::try_from(frame_bytes)
+            .map_err(|_| GVError::LimitExceeded { what: "frame bytes", value: frame_bytes as u64, limit: u32::MAX as u64 })?;
+
+        let file_size = reader.seek(SeekFrom::End(0))?;
+        // a valid block is never longer than the lz4 bound of frame bytes
+        let window_size = lz4_flex::block::get_maximum_output_size(frame_bytes) as u64;
+        let mut window = Vec::new();
+        let mut output = vec![0; frame_bytes];
+        let mut address_size_blocks = Vec::new();
+        let mut address = HEADER_SIZE as u64;
+        while frame_bytes > 0 && address < file_size {
+            window.clear();
+            reader.seek(SeekFrom::Start(address))?;
+            reader.by_ref().take(window_size).read_to_end(&mut window)?;
+            let Some(size) = lz4_block_len(&window, frame_bytes) else {
+                break;
+            };
+            if !matches!(lz4_flex::block::decompress_into(&window[..size], &mut output), Ok(len) if len == frame_bytes) {
+                break;
+            }
+            if address_size_blocks.len() as u64 >= options.max_frame_count as u64 {
+                return Err(GVError::LimitExceeded {
+                    what: "frame count",
+                    value: address_size_blocks.len() as u64 + 1,
+                    limit: options.max_frame_count as u64,
+                });
+            }
+            address_size_blocks.push(GVAddressSizeBlock { address, size: size as u64 });
+            address += size as u64;
+        }
+        header.frame_count = address_size_blocks.len() as u32;
+
+        Ok(GVVideo {
+            header,
+            address_size_blocks,
+            reader,
+            scratch: Scratch::default(),
+            preloaded: None,
+            options,
+            file_size,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GVFormat, GVWriter};
+    use std::io::Cursor;
+
+    // SMPTE BAR with alpha gradient
+    const TEST_ALPHA_GV: &[u8; 4857] = include_bytes!("../test_asset/test-alpha.gv");
+    // 10px 5sec 1fps
+    const TEST_10PX_GV: &[u8; 474] = include_bytes!("../test_asset/test-10px.gv");
+
+    /// file as left by a recorder which stopped before writing the address table
+    fn unfinished(data: &[u8], frame_count: u32) -> Vec<u8> {
+        let mut data = data[..data.len() - frame_count as usize * 16].to_vec();
+        data[8..12].copy_from_slice(&0u32.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn recover_assets() {
+        for data in [&TEST_10PX_GV[..], &TEST_ALPHA_GV[..]] {
+            let mut original = GVVideo::load(Cursor::new(data)).unwrap();
+            let frame_count = original.get_frame_count();
+
+            // intact file, scanning stops at the address table
+            let recovered = GVVideo::recover(Cursor::new(data)).unwrap();
+            assert_eq!(recovered.address_size_blocks, original.address_size_blocks);
+            assert_eq!(recovered.header, original.header);
+
+            let mut recovered = GVVideo::recover(Cursor::new(unfinished(data, frame_count))).unwrap();
+            assert_eq!(recovered.address_size_blocks, original.address_size_blocks);
+            for frame_id in 0..frame_count {
+                assert_eq!(recovered.read_frame(frame_id).unwrap(), original.read_frame(frame_id).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn partial_last_frame() {
+        let mut writer = GVWriter::new(Cursor::new(Vec::new()), 16, 16, 30.0, GVFormat::DXT5).unwrap();
+        let frames: Vec<Vec<u8>> = (0..4u8).map(|i| (0..256).map(|j| (j as u8 / 16).wrapping_mul(i)).collect()).collect();
+        for frame in &frames {
+            writer.write_frame(frame).unwrap();
+        }
+        let last = *writer.get_address_size_blocks().last().unwrap();
+        let data = writer.finish().unwrap().into_inner();
+        let data = &unfinished(&data, 4)[..(last.address + last.size / 2) as usize];
+
+        let mut video = GVVideo::recover(Cursor::new(data)).unwrap();
+        assert_eq!(video.get_frame_count(), 3);
+        for (frame_id, frame) in frames.iter().take(3).enumerate() {
+            assert_eq!(&video.read_frame_compressed(frame_id as u32).unwrap(), frame);
+        }
+
+        // header only
+        let video = GVVideo::recover(Cursor::new(&data[..HEADER_SIZE])).unwrap();
+        assert_eq!(video.get_frame_count(), 0);
+    }
+
+    #[test]
+    fn block_len() {
+        let data: Vec<u8> = (0..1000).map(|i| (i % 7) as u8).collect();
+        let mut compressed = lz4_flex::block::compress(&data);
+        let len = compressed.len();
+        compressed.extend_from_slice(&[0xff; 32]);
+        assert_eq!(lz4_block_len(&compressed, 1000), Some(len));
+        assert_eq!(lz4_block_len(&compressed, 999), None);
+        assert_eq!(lz4_block_len(&compressed[..len - 1], 1000), None);
+    }
+}
@@ -0,0 +1,348 @@
+// GV video readable from several threads at once
+//
+// frames are read with positional reads (no shared seek position), header and address table are shared via `Arc`.
+
+use std::{
+    io::{self, Read, Seek, SeekFrom},
+    sync::Arc,
+};
+#[cfg(any(unix, windows))]
+use std::{fs::File, path::Path};
+
+use crate::{decode_bc, decompress_frame, GVAddressSizeBlock, GVError, GVFormat, GVHeader, GVLoadOptions, GVVideo};
+
+/// source of positional reads for `SharedGVVideo`
+pub trait ReadAt: Send + Sync {
+    /// read exactly `buf.len()` bytes at `offset`
+    fn read_exact_at(&self, buf: &mut [u8], offset: u64) -> io::Result<()>;
+
+    /// total size in bytes
+    fn size(&self) -> io::Result<u64>;
+}
+
+// positional reads exist only on unix and windows, other targets (wasm32) load from memory
+#[cfg(any(unix, windows))]
+impl ReadAt for File {
+    #[cfg(unix)]
+    fn read_exact_at(&self, buf: &mut [u8], offset: u64) -> io::Result<()> {
+        std::os::unix::fs::FileExt::read_exact_at(self, buf, offset)
+    }
+
+    #[cfg(windows)]
+    fn read_exact_at(&self, mut buf: &mut [u8], mut offset: u64) -> io::Result<()> {
+        // seek_read moves the file cursor, but never reads from it
+        while !buf.is_empty() {
+            match std::os::windows::fs::FileExt::seek_read(self, buf, offset) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(n) => {
+                    buf = &mut buf[n..];
+                    offset += n as u64;
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.metadata()?.len())
+    }
+}
+
+fn read_exact_at_bytes(bytes: &[u8], buf: &mut [u8], offset: u64) -> io::Result<()> {
+    let data = usize::try_from(offset)
+        .ok()
+        .and_then(|start| bytes.get(start..start.checked_add(buf.len())?))
+        .ok_or(io::ErrorKind::UnexpectedEof)?;
+    buf.copy_from_slice(data);
+    Ok(())
+}
+
+impl ReadAt for &[u8] {
+    fn read_exact_at(&self, buf: &mut [u8], offset: u64) -> io::Result<()> {
+        read_exact_at_bytes(self, buf, offset)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.len() as u64)
+    }
+}
+
+impl ReadAt for Vec<u8> {
+    fn read_exact_at(&self, buf: &mut [u8], offset: u64) -> io::Result<()> {
+        read_exact_at_bytes(self, buf, offset)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.len() as u64)
+    }
+}
+
+impl ReadAt for Arc<[u8]> {
+    fn read_exact_at(&self, buf: &mut [u8], offset: u64) -> io::Result<()> {
+        read_exact_at_bytes(self, buf, offset)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.len() as u64)
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl ReadAt for memmap2::Mmap {
+    fn read_exact_at(&self, buf: &mut [u8], offset: u64) -> io::Result<()> {
+        read_exact_at_bytes(self, buf, offset)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.len() as u64)
+    }
+}
+
+/// Read + Seek view of a `ReadAt` source, used to load header and address table
+struct ReadAtCursor<'a, Source: ReadAt> {
+    source: &'a Source,
+    position: u64,
+    size: u64,
+}
+
+impl<Source: ReadAt> Read for ReadAtCursor<'_, Source> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = (buf.len() as u64).min(self.size.saturating_sub(self.position)) as usize;
+        self.source.read_exact_at(&mut buf[..len], self.position)?;
+        self.position += len as u64;
+        Ok(len)
+    }
+}
+
+impl<Source: ReadAt> Seek for ReadAtCursor<'_, Source> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.size.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+        self.position = position.ok_or(io::ErrorKind::InvalidInput)?;
+        Ok(self.position)
+    }
+}
+
+/// GV video whose read methods take `&self`, so frames can be decoded on several threads at once
+///
+/// cloning is cheap, clones share the source and the address table
+pub struct SharedGVVideo<Source: ReadAt> {
+    pub header: GVHeader,
+    pub address_size_blocks: Arc<[GVAddressSizeBlock]>,
+    source: Arc<Source>,
+    /// source size at load time
+    source_size: u64,
+    options: GVLoadOptions,
+}
+
+impl<Source: ReadAt> Clone for SharedGVVideo<Source> {
+    fn clone(&self) -> Self {
+        SharedGVVideo {
+            header: self.header.clone(),
+            address_size_blocks: Arc::clone(&self.address_size_blocks),
+            source: Arc::clone(&self.source),
+            source_size: self.source_size,
+            options: self.options,
+        }
+    }
+}
+
+impl<Source: ReadAt> std::fmt::Debug for SharedGVVideo<Source> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SharedGVVideo")
+            .field("header", &self.header)
+            .field("address_size_blocks", &self.address_size_blocks)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(any(unix, windows))]
+impl SharedGVVideo<File> {
+    pub fn open(file_path: impl AsRef<Path>) -> Result<SharedGVVideo<File>, GVError> {
+        SharedGVVideo::load(File::open(file_path)?)
+    }
+}
+
+impl<Source: ReadAt> SharedGVVideo<Source> {
+    /// load header and address table from `source` (`File`, `Vec<u8>`, `&[u8]`, `Arc<[u8]>`, ...)
+    pub fn load(source: Source) -> Result<SharedGVVideo<Source>, GVError> {
+        SharedGVVideo::load_with_options(source, GVLoadOptions::default())
+    }
+
+    /// load header and address table, failing with `GVError::LimitExceeded` when the header exceeds `options`
+    pub fn load_with_options(source: Source, options: GVLoadOptions) -> Result<SharedGVVideo<Source>, GVError> {
+        let size = source.size()?;
+        let cursor = ReadAtCursor { source: &source, position: 0, size };
+        let GVVideo { header, address_size_blocks, .. } = GVVideo::load_with_options(cursor, options)?;
+        Ok(SharedGVVideo {
+            header,
+            address_size_blocks: address_size_blocks.into(),
+            source: Arc::new(source),
+            source_size: size,
+            options,
+        })
+    }
+
+    /// lz4 decompressed frame size, see `GVHeader::expected_frame_bytes`
+    fn uncompressed_size(&self) -> usize {
+        self.header.expected_frame_bytes()
+    }
+
+    /// read raw lz4 block of the frame
+    pub fn read_lz4_block(&self, frame_id: u32) -> Result<Vec<u8>, GVError> {
+        if frame_id >= self.header.frame_count {
+            return Err(GVError::FrameOutOfRange { id: frame_id, count: self.header.frame_count });
+        }
+
+        let block = self.address_size_blocks[frame_id as usize];
+        let size = self.options.check_block(&block)?;
+        // corrupt address table must not cause huge allocation
+        if block.address.checked_add(block.size).map_or(true, |end| end > self.source_size) {
+            return Err(GVError::Truncated);
+        }
+        let mut data = vec![0; size];
+        self.source.read_exact_at(&mut data, block.address)?;
+        Ok(data)
+    }
+
+    /// decompress lz4 block and decode dxt, then return decompressed frame data (BGRA u32)
+    pub fn read_frame(&self, frame_id: u32) -> Result<Vec<u32>, GVError> {
+        let width = self.header.width as usize;
+        let height = self.header.height as usize;
+        let mut result = vec![0; width * height];
+        self.read_frame_into(frame_id, &mut result)?;
+        Ok(result)
+    }
+
+    /// decompress lz4 block and decode dxt into `buffer` (BGRA u32, at least width * height)
+    pub fn read_frame_into(&self, frame_id: u32, buffer: &mut [u32]) -> Result<(), GVError> {
+        let width = self.header.width as usize;
+        let height = self.header.height as usize;
+        if buffer.len() < width * height {
+            return Err(GVError::InvalidFrameSize { expected: width * height, actual: buffer.len() });
+        }
+
+        let data = self.read_frame_compressed(frame_id)?;
+        decode_bc(&self.header, &data, buffer)
+    }
+
+    /// decompress lz4 block, then return compressed frame data (BC1, BC2, BC3, BC7)
+    pub fn read_frame_compressed(&self, frame_id: u32) -> Result<Vec<u8>, GVError> {
+        let mut result = vec![0; self.uncompressed_size()];
+        self.read_frame_compressed_into(frame_id, &mut result)?;
+        Ok(result)
+    }
+
+    /// decompress lz4 block into `buffer`, then return its length (compressed frame data, BC1, BC2, BC3, BC7)
+    pub fn read_frame_compressed_into(&self, frame_id: u32, buffer: &mut [u8]) -> Result<usize, GVError> {
+        let lz4 = self.read_lz4_block(frame_id)?;
+        decompress_frame(frame_id, &lz4, buffer, self.uncompressed_size())
+    }
+
+    pub fn get_duration(&self) -> std::time::Duration {
+        self.header.duration()
+    }
+
+    pub fn get_width(&self) -> u32 {
+        self.header.width
+    }
+
+    pub fn get_height(&self) -> u32 {
+        self.header.height
+    }
+
+    pub fn get_resolution(&self) -> (u32, u32) {
+        (self.header.width, self.header.height)
+    }
+
+    pub fn get_frame_count(&self) -> u32 {
+        self.header.frame_count
+    }
+
+    pub fn get_fps(&self) -> f32 {
+        self.header.fps
+    }
+
+    pub fn get_format(&self) -> GVFormat {
+        self.header.format
+    }
+
+    pub fn get_frame_bytes(&self) -> u32 {
+        self.header.frame_bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const TEST_ALPHA_GV: &[u8; 4857] = include_bytes!("../test_asset/test-alpha.gv");
+    // 10px 5sec 1fps
+    const TEST_10PX_GV: &[u8; 474] = include_bytes!("../test_asset/test-10px.gv");
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[cfg(any(unix, windows))]
+    #[test]
+    fn concurrent_reads() {
+        assert_send_sync::<SharedGVVideo<File>>();
+        assert_send_sync::<SharedGVVideo<Vec<u8>>>();
+
+        let mut expected = GVVideo::load(Cursor::new(TEST_ALPHA_GV)).unwrap();
+        let expected: Vec<Vec<u32>> = (0..expected.get_frame_count()).map(|i| expected.read_frame(i).unwrap()).collect();
+
+        let file = SharedGVVideo::open(concat!(env!("CARGO_MANIFEST_DIR"), "/test_asset/test-alpha.gv")).unwrap();
+        let memory = SharedGVVideo::load(TEST_ALPHA_GV.to_vec()).unwrap();
+        assert_eq!(file.header, memory.header);
+        assert_eq!(file.address_size_blocks, memory.address_size_blocks);
+
+        std::thread::scope(|scope| {
+            for thread in 0..4 {
+                let (file, memory, expected) = (file.clone(), &memory, &expected);
+                scope.spawn(move || {
+                    // each thread starts at a different frame
+                    for i in 0..expected.len() {
+                        let id = (i + thread) % expected.len();
+                        assert_eq!(file.read_frame(id as u32).unwrap(), expected[id]);
+                        assert_eq!(memory.read_frame(id as u32).unwrap(), expected[id]);
+                    }
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn same_as_gv_video() {
+        let mut video = GVVideo::load(Cursor::new(TEST_10PX_GV)).unwrap();
+        let shared = SharedGVVideo::load(&TEST_10PX_GV[..]).unwrap();
+        assert_eq!(shared.get_resolution(), video.get_resolution());
+        assert_eq!(shared.get_duration(), video.get_duration());
+        for i in 0..5 {
+            assert_eq!(shared.read_frame_compressed(i).unwrap(), video.read_frame_compressed(i).unwrap());
+        }
+        assert!(matches!(shared.read_frame(5), Err(GVError::FrameOutOfRange { id: 5, count: 5 })));
+
+        // borrowed bytes, not only 'static
+        let owned = TEST_10PX_GV.to_vec();
+        let borrowed = SharedGVVideo::load(&owned[..]).unwrap();
+        assert_eq!(borrowed.read_frame_compressed(4).unwrap(), video.read_frame_compressed(4).unwrap());
+    }
+
+    #[test]
+    fn truncated() {
+        let res = SharedGVVideo::load(TEST_10PX_GV[..20].to_vec());
+        assert!(matches!(res, Err(GVError::Truncated)));
+
+        let mut shared = SharedGVVideo::load(TEST_10PX_GV.to_vec()).unwrap();
+        let mut blocks = shared.address_size_blocks.to_vec();
+        blocks[1].address = u64::MAX;
+        shared.address_size_blocks = blocks.into();
+        assert!(matches!(shared.read_frame(1), Err(GVError::Truncated)));
+    }
+}
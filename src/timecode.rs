@@ -0,0 +1,209 @@
+// SMPTE timecode
+//
+// timecode counts frames at the nominal (integer) rate, e.g. 30 for 29.97. drop-frame timecode (29.97, 59.94)
+// skips frame numbers 0 and 1 (0..4 at 59.94) at the start of every minute except every tenth minute, so the
+// timecode stays in step with wall-clock time. drop-frame is written with ';' before the frames, e.g. "00:01:00;02".
+
+use std::{
+    fmt,
+    io::{Read, Seek},
+    str::FromStr,
+};
+
+use crate::{FrameRate, GVError, GVVideo};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct Timecode {
+    pub hours: u32,
+    pub minutes: u8,
+    pub seconds: u8,
+    /// wider than the other fields, nominal rates over 255 fps exist
+    pub frames: u32,
+    pub drop_frame: bool,
+}
+
+/// frames per timecode second, e.g. 30 for 29.97
+fn nominal_fps(rate: FrameRate) -> u32 {
+    (rate.as_f64().round() as u32).max(1)
+}
+
+/// frame numbers dropped per minute, 0 when the rate has no drop-frame timecode
+fn dropped_per_minute(rate: FrameRate) -> u32 {
+    let nominal = nominal_fps(rate);
+    if rate.is_ntsc() && nominal % 30 == 0 { nominal / 15 } else { 0 }
+}
+
+impl Timecode {
+    /// drop-frame is used when `drop_frame` is true and the rate supports it (29.97, 59.94, ...)
+    pub fn from_frame(frame_id: u32, rate: FrameRate, drop_frame: bool) -> Timecode {
+        let nominal = nominal_fps(rate) as u64;
+        let drop = if drop_frame { dropped_per_minute(rate) as u64 } else { 0 };
+        let mut frame = frame_id as u64;
+        if drop > 0 {
+            let per_10_minutes = nominal * 600 - drop * 9;
+            let per_minute = nominal * 60 - drop;
+            let (tens, rest) = (frame / per_10_minutes, frame % per_10_minutes);
+            frame += drop * 9 * tens;
+            if rest > drop {
+                frame += drop * ((rest - drop) / per_minute);
+            }
+        }
+        Timecode {
+            hours: (frame / (nominal * 3600)) as u32,
+            minutes: (frame / (nominal * 60) % 60) as u8,
+            seconds: (frame / nominal % 60) as u8,
+            frames: (frame % nominal) as u32,
+            drop_frame: drop > 0,
+        }
+    }
+
+    /// frame id of the timecode at `rate`
+    pub fn to_frame(&self, rate: FrameRate) -> Result<u32, GVError> {
+        let nominal = nominal_fps(rate) as u64;
+        let drop = dropped_per_minute(rate) as u64;
+        let invalid = || GVError::InvalidTimecode(self.to_string());
+        if self.minutes >= 60 || self.seconds >= 60 || self.frames as u64 >= nominal {
+            return Err(invalid());
+        }
+        if self.drop_frame && drop == 0 {
+            return Err(invalid());
+        }
+
+        let total_minutes = self.hours as u64 * 60 + self.minutes as u64;
+        let mut frame = (total_minutes * 60 + self.seconds as u64) * nominal + self.frames as u64;
+        if self.drop_frame {
+            // dropped frame numbers do not exist
+            if self.seconds == 0 && (self.frames as u64) < drop && self.minutes % 10 != 0 {
+                return Err(invalid());
+            }
+            frame -= drop * (total_minutes - total_minutes / 10);
+        }
+        u32::try_from(frame).map_err(|_| invalid())
+    }
+}
+
+impl fmt::Display for Timecode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let separator = if self.drop_frame { ';' } else { ':' };
+        write!(f, "{:02}:{:02}:{:02}{}{:02}", self.hours, self.minutes, self.seconds, separator, self.frames)
+    }
+}
+
+impl FromStr for Timecode {
+    type Err = GVError;
+
+    /// "HH:MM:SS:FF" (non-drop-frame) or "HH:MM:SS;FF" / "HH;MM;SS;FF" / "HH:MM:SS.FF" (drop-frame)
+    fn from_str(s: &str) -> Result<Timecode, GVError> {
+        let invalid = || GVError::InvalidTimecode(s.to_string());
+        let parts: Vec<&str> = s.trim().split([':', ';', '.']).collect();
+        let [hours, minutes, seconds, frames] = parts.as_slice() else {
+            return Err(invalid());
+        };
+        let number = |part: &str| {
+            if part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(invalid());
+            }
+            part.parse::<u32>().map_err(|_| invalid())
+        };
+        let small = |part: &str| number(part).and_then(|n| u8::try_from(n).map_err(|_| invalid()));
+        Ok(Timecode {
+            hours: number(hours)?,
+            minutes: small(minutes)?,
+            seconds: small(seconds)?,
+            frames: number(frames)?,
+            drop_frame: s.contains([';', '.']),
+        })
+    }
+}
+
+impl<Reader: Read + Seek> GVVideo<Reader> {
+    /// timecode of frame, `drop_frame` is used only for 29.97 / 59.94 videos, see `Timecode::from_frame`
+    pub fn timecode_of(&self, frame_id: u32, drop_frame: bool) -> Timecode {
+        Timecode::from_frame(frame_id, self.get_frame_rate(), drop_frame)
+    }
+
+    /// frame id of timecode such as "01:02:03:04" or "01:02:03;04" (drop-frame), not clamped to frame count
+    pub fn frame_of_timecode(&self, timecode: &str) -> Result<u32, GVError> {
+        timecode.parse::<Timecode>()?.to_frame(self.get_frame_rate())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    // 10px 5sec 1fps
+    const TEST_10PX_GV: &[u8; 474] = include_bytes!("../test_asset/test-10px.gv");
+
+    fn rate(fps: f32) -> FrameRate {
+        FrameRate::from_fps(fps)
+    }
+
+    fn round_trip(frame_id: u32, fps: f32, drop_frame: bool, expected: &str) {
+        let timecode = Timecode::from_frame(frame_id, rate(fps), drop_frame);
+        assert_eq!(timecode.to_string(), expected);
+        assert_eq!(expected.parse::<Timecode>().unwrap(), timecode);
+        assert_eq!(timecode.to_frame(rate(fps)).unwrap(), frame_id);
+    }
+
+    #[test]
+    fn non_drop_frame() {
+        round_trip(0, 25.0, false, "00:00:00:00");
+        round_trip(90000 + 60 * 25 * 2 + 3 * 25 + 4, 25.0, false, "01:02:03:04");
+        round_trip(86400 * 24, 24.0, false, "24:00:00:00");
+        // 29.97 without drop-frame counts 30 frames per timecode second
+        round_trip(1800, 29.97, false, "00:01:00:00");
+        // no drop-frame for integer rates
+        round_trip(1800, 30.0, true, "00:01:00:00");
+        // high speed capture rates over 255 fps
+        round_trip(299, 300.0, false, "00:00:00:299");
+        round_trip(1000 * 61 + 999, 1000.0, false, "00:01:01:999");
+    }
+
+    #[test]
+    fn drop_frame() {
+        round_trip(1799, 29.97, true, "00:00:59;29");
+        round_trip(1800, 29.97, true, "00:01:00;02");
+        round_trip(17981, 29.97, true, "00:09:59;29");
+        round_trip(17982, 29.97, true, "00:10:00;00");
+        round_trip(107892, 29.97, true, "01:00:00;00");
+        round_trip(3600, 59.94, true, "00:01:00;04");
+
+        // every frame round-trips
+        for frame_id in 0..20000 {
+            let timecode = Timecode::from_frame(frame_id, rate(29.97), true);
+            assert_eq!(timecode.to_frame(rate(29.97)).unwrap(), frame_id);
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        let parse = |s: &str, fps: f32| s.parse::<Timecode>().and_then(|tc| tc.to_frame(rate(fps)));
+        // dropped frame number
+        assert!(matches!(parse("00:01:00;01", 29.97), Err(GVError::InvalidTimecode(_))));
+        assert_eq!(parse("00:10:00;01", 29.97).unwrap(), 17983);
+        // drop-frame at non-NTSC rate
+        assert!(parse("00:00:01;00", 25.0).is_err());
+        // frames over nominal rate
+        assert!(parse("00:00:01:25", 25.0).is_err());
+        assert!(parse("00:60:00:00", 25.0).is_err());
+        assert!(parse("00:00:00", 25.0).is_err());
+        assert!(parse("00:00:00:+1", 25.0).is_err());
+        assert!(parse("aa:00:00:00", 25.0).is_err());
+    }
+
+    #[test]
+    fn video_timecode() {
+        let video = GVVideo::load(Cursor::new(TEST_10PX_GV)).unwrap();
+        assert_eq!(video.timecode_of(4, true).to_string(), "00:00:04:00");
+        assert_eq!(video.frame_of_timecode("00:00:03:00").unwrap(), 3);
+        assert!(video.frame_of_timecode("00:00:03:01").is_err());
+
+        // both modes at 29.97
+        let writer = crate::GVWriter::new(Cursor::new(Vec::new()), 4, 4, 29.97, crate::GVFormat::DXT1).unwrap();
+        let video = GVVideo::load(Cursor::new(writer.finish().unwrap().into_inner())).unwrap();
+        assert_eq!(video.timecode_of(1800, true).to_string(), "00:01:00;02");
+        assert_eq!(video.timecode_of(1800, false).to_string(), "00:01:00:00");
+    }
+}
@@ -0,0 +1,157 @@
+// mapping between frame ids and time
+//
+// frame k is shown during [timestamp_of(k), timestamp_of(k + 1)). times are computed from the rational
+// `FrameRate` in integer nanoseconds, so they do not drift even for multi-hour videos or NTSC rates.
+
+use std::{
+    io::{Read, Seek},
+    ops::Range,
+    time::Duration,
+};
+
+use crate::{FrameRate, GVHeader, GVVideo};
+
+/// tolerance for floating point error when flooring a position in frames to frame id (`GVPlayer`)
+pub(crate) const FRAME_EPSILON: f64 = 1e-6;
+
+/// how `frame_at` maps a time between two frame starts to a frame id
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum Rounding {
+    /// frame being shown at the time
+    #[default]
+    Floor,
+    /// frame whose start is closest to the time
+    Nearest,
+    /// first frame starting at or after the time
+    Ceil,
+}
+
+impl GVHeader {
+    /// rational frame rate recognized from `fps`, see `FrameRate::from_fps`
+    pub fn frame_rate(&self) -> FrameRate {
+        FrameRate::from_fps(self.fps)
+    }
+
+    /// frame id at time (not clamped to frame count, 0 when fps is not positive)
+    pub fn frame_at(&self, time: Duration, rounding: Rounding) -> u32 {
+        u32::try_from(self.frame_rate().frame_at(time, rounding)).unwrap_or(u32::MAX)
+    }
+
+    /// presentation time of frame (zero when fps is not positive)
+    pub fn timestamp_of(&self, frame_id: u32) -> Duration {
+        self.frame_rate().timestamp_of(frame_id as u64)
+    }
+
+    /// frames shown during time range (clamped to frame count)
+    pub fn frame_range_for(&self, time_range: Range<Duration>) -> Range<u32> {
+        if time_range.end <= time_range.start {
+            return 0..0;
+        }
+        let count = self.frame_count;
+        let start = self.frame_at(time_range.start, Rounding::Floor).min(count);
+        let end = self.frame_at(time_range.end, Rounding::Ceil).min(count);
+        start..end.max(start)
+    }
+
+    /// frame count / fps (zero when fps is not positive)
+    pub fn duration(&self) -> Duration {
+        self.timestamp_of(self.frame_count)
+    }
+}
+
+impl<Reader: Read + Seek> GVVideo<Reader> {
+    /// frame id at time (not clamped to frame count), see `GVHeader::frame_at`
+    pub fn frame_at(&self, time: Duration, rounding: Rounding) -> u32 {
+        self.header.frame_at(time, rounding)
+    }
+
+    /// presentation time of frame
+    pub fn timestamp_of(&self, frame_id: u32) -> Duration {
+        self.header.timestamp_of(frame_id)
+    }
+
+    pub fn get_frame_rate(&self) -> FrameRate {
+        self.header.frame_rate()
+    }
+
+    /// frames shown during time range (clamped to frame count)
+    pub fn frame_range_for(&self, time_range: Range<Duration>) -> Range<u32> {
+        self.header.frame_range_for(time_range)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GVFormat;
+
+    fn make_header(frame_count: u32, fps: f32) -> GVHeader {
+        GVHeader { width: 4, height: 4, frame_count, fps, format: GVFormat::DXT1, frame_bytes: 8 }
+    }
+
+    #[test]
+    fn rounding() {
+        let header = make_header(100, 10.0);
+        let t = Duration::from_millis(1240);
+        assert_eq!(header.frame_at(t, Rounding::Floor), 12);
+        assert_eq!(header.frame_at(t, Rounding::Nearest), 12);
+        assert_eq!(header.frame_at(t, Rounding::Ceil), 13);
+        let t = Duration::from_millis(1260);
+        assert_eq!(header.frame_at(t, Rounding::Nearest), 13);
+
+        // exactly at frame start
+        let t = Duration::from_millis(1200);
+        assert_eq!(header.frame_at(t, Rounding::Floor), 12);
+        assert_eq!(header.frame_at(t, Rounding::Ceil), 12);
+
+        // not clamped, so reading beyond the end is an error
+        assert_eq!(header.frame_at(Duration::from_secs(100), Rounding::Floor), 1000);
+        assert_eq!(make_header(10, 0.0).frame_at(Duration::from_secs(1), Rounding::Floor), 0);
+    }
+
+    #[test]
+    fn frame_range() {
+        let header = make_header(100, 10.0);
+        let range = |start, end| header.frame_range_for(Duration::from_millis(start)..Duration::from_millis(end));
+        assert_eq!(range(0, 1000), 0..10);
+        assert_eq!(range(1050, 1250), 10..13);
+        assert_eq!(range(9950, 20000), 99..100);
+        assert_eq!(range(500, 500), 0..0);
+        assert_eq!(range(20000, 30000), 100..100);
+    }
+
+    #[test]
+    fn stable_at_24h() {
+        for fps in [24.0, 25.0, 30.0, 60.0, 120.0] {
+            let frame_count = (fps * 86400.0) as u32 + 100;
+            let header = make_header(frame_count, fps);
+            let day = fps as u32 * 86400;
+            for frame_id in day - 10..day + 10 {
+                let timestamp = header.timestamp_of(frame_id);
+                assert_eq!(header.frame_at(timestamp, Rounding::Floor), frame_id, "{} fps", fps);
+                assert_eq!(header.frame_at(timestamp, Rounding::Ceil), frame_id, "{} fps", fps);
+                assert_eq!(header.frame_at(timestamp + Duration::from_nanos(1), Rounding::Floor), frame_id);
+                assert_eq!(header.frame_at(timestamp - Duration::from_micros(1), Rounding::Floor), frame_id - 1);
+                assert_eq!(header.frame_range_for(timestamp..timestamp + Duration::from_micros(1)), frame_id..frame_id + 1);
+            }
+            assert_eq!(header.timestamp_of(day), Duration::from_secs(86400));
+        }
+    }
+
+    #[test]
+    fn ntsc() {
+        let header = make_header(100000, 29.97);
+        assert_eq!(header.frame_rate(), FrameRate::new(30000, 1001).unwrap());
+        assert_eq!(header.timestamp_of(30000), Duration::from_secs(1001));
+        assert_eq!(header.frame_at(Duration::from_secs(1001), Rounding::Floor), 30000);
+        // f32 fps would be 1 frame behind here
+        assert_eq!((1001.0f32 * 29.97f32) as u32, 29999);
+    }
+
+    #[test]
+    fn duration() {
+        assert_eq!(make_header(5, 1.0).duration(), Duration::from_secs(5));
+        assert_eq!(make_header(90, 30.0).duration(), Duration::from_secs(3));
+        assert_eq!(make_header(90, 0.0).duration(), Duration::ZERO);
+    }
+}
@@ -0,0 +1,242 @@
+// consistency checks of a loaded video
+//
+// `GVVideo::load` only reads the header and the address table, so a damaged file is noticed frame by frame.
+// `validate()` checks everything up front and reports every problem instead of stopping at the first one.
+
+use std::{
+    fmt,
+    io::{Read, Seek, SeekFrom},
+};
+
+use crate::{GVError, GVHeader, GVVideo, HEADER_SIZE};
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum GVIssue {
+    /// header frame_bytes differs from the size implied by width, height and format
+    FrameBytesMismatch { stored: u32, expected: usize },
+    /// fps is zero, negative, NaN or infinite
+    InvalidFps(f32),
+    /// width or height is zero
+    EmptyResolution { width: u32, height: u32 },
+    /// file is smaller than header, frames and address table need
+    FileTooSmall { file_size: u64, min_size: u64 },
+    ZeroSizeFrame { id: u32 },
+    /// block starts inside the header
+    OverlapsHeader { id: u32, address: u64 },
+    /// block extends into the address table or beyond the end of file
+    OutOfRange { id: u32, address: u64, size: u64 },
+    /// blocks of two frames share bytes
+    Overlap { id: u32, other: u32 },
+    /// lz4 block could not be decompressed
+    Lz4Error { id: u32, reason: String },
+    /// lz4 block decompressed to a size other than frame bytes
+    Lz4LengthMismatch { id: u32, expected: usize, actual: usize },
+}
+
+impl fmt::Display for GVIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GVIssue::FrameBytesMismatch { stored, expected } => {
+                write!(f, "header frame bytes is {}, but width, height and format imply {}", stored, expected)
+            }
+            GVIssue::InvalidFps(fps) => write!(f, "invalid fps: {}", fps),
+            GVIssue::EmptyResolution { width, height } => write!(f, "empty resolution: {}x{}", width, height),
+            GVIssue::FileTooSmall { file_size, min_size } => {
+                write!(f, "file size {} is smaller than {} needed for header, frames and address table", file_size, min_size)
+            }
+            GVIssue::ZeroSizeFrame { id } => write!(f, "frame {}: zero size", id),
+            GVIssue::OverlapsHeader { id, address } => write!(f, "frame {}: address {} is inside the header", id, address),
+            GVIssue::OutOfRange { id, address, size } => {
+                write!(f, "frame {}: address {} size {} is outside of the frame data", id, address, size)
+            }
+            GVIssue::Overlap { id, other } => write!(f, "frame {}: overlaps frame {}", id, other),
+            GVIssue::Lz4Error { id, reason } => write!(f, "frame {}: lz4 error: {}", id, reason),
+            GVIssue::Lz4LengthMismatch { id, expected, actual } => {
+                write!(f, "frame {}: lz4 block decompressed to {} bytes, expected {}", id, actual, expected)
+            }
+        }
+    }
+}
+
+/// every frame has at least one byte of lz4 data and 16 bytes of address table
+pub(crate) fn min_file_size(header: &GVHeader) -> u64 {
+    HEADER_SIZE as u64 + header.frame_count as u64 * 17
+}
+
+/// result of `GVVideo::validate()`
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct GVValidationReport {
+    pub issues: Vec<GVIssue>,
+}
+
+impl GVValidationReport {
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl<Reader: Read + Seek> GVVideo<Reader> {
+    /// check header, address table and every lz4 block, I/O errors are returned as `Err`
+    pub fn validate(&mut self) -> Result<GVValidationReport, GVError> {
+        let mut issues = Vec::new();
+        let header = self.header.clone();
+
+        if header.width == 0 || header.height == 0 {
+            issues.push(GVIssue::EmptyResolution { width: header.width, height: header.height });
+        }
+        if !(header.fps > 0.0 && header.fps.is_finite()) {
+            issues.push(GVIssue::InvalidFps(header.fps));
+        }
+        let frame_bytes = header.expected_frame_bytes();
+        if header.frame_bytes as usize != frame_bytes {
+            issues.push(GVIssue::FrameBytesMismatch { stored: header.frame_bytes, expected: frame_bytes });
+        }
+
+        // frame data lies between the header and the address table
+        let file_size = self.reader.seek(SeekFrom::End(0))?;
+        let min_size = min_file_size(&header);
+        if file_size < min_size {
+            issues.push(GVIssue::FileTooSmall { file_size, min_size });
+        }
+        let table_start = file_size.saturating_sub(header.frame_count as u64 * 16);
+        let mut readable = vec![false; self.address_size_blocks.len()];
+        for (id, block) in self.address_size_blocks.iter().enumerate() {
+            let id = id as u32;
+            if block.size == 0 {
+                issues.push(GVIssue::ZeroSizeFrame { id });
+            }
+            if block.address < HEADER_SIZE as u64 {
+                issues.push(GVIssue::OverlapsHeader { id, address: block.address });
+            } else if block.address.checked_add(block.size).map_or(true, |end| end > table_start) {
+                issues.push(GVIssue::OutOfRange { id, address: block.address, size: block.size });
+            } else {
+                readable[id as usize] = block.size > 0;
+            }
+        }
+
+        // sweep in address order, each block is reported once against the block reaching furthest before it,
+        // so a table pointing every frame at the same bytes gives one issue per frame, not per pair
+        let mut order: Vec<u32> = (0..header.frame_count).filter(|&id| readable[id as usize]).collect();
+        order.sort_by_key(|&id| (self.address_size_blocks[id as usize].address, id));
+        let mut furthest: Option<(u64, u32)> = None;
+        for &id in &order {
+            let block = self.address_size_blocks[id as usize];
+            let end = block.address + block.size;
+            match furthest {
+                Some((furthest_end, other)) if block.address < furthest_end => {
+                    issues.push(GVIssue::Overlap { id: id.min(other), other: id.max(other) });
+                    if end > furthest_end {
+                        furthest = Some((end, id));
+                    }
+                }
+                _ => furthest = Some((end, id)),
+            }
+        }
+
+        // output one byte larger than expected, so longer output is detected as well
+        let mut buffer = Vec::new();
+        for id in (0..header.frame_count).filter(|&id| readable[id as usize]) {
+            if buffer.is_empty() {
+                buffer = vec![0; frame_bytes + 1];
+            }
+            self.read_lz4_block(id)?;
+            let lz4 = self.preloaded.as_ref().and_then(|p| p.block(id)).unwrap_or(&self.scratch.lz4);
+            match lz4_flex::block::decompress_into(lz4, &mut buffer) {
+                Ok(len) if len == frame_bytes => {}
+                Ok(len) => issues.push(GVIssue::Lz4LengthMismatch { id, expected: frame_bytes, actual: len }),
+                Err(err) => issues.push(GVIssue::Lz4Error { id, reason: err.to_string() }),
+            }
+        }
+
+        Ok(GVValidationReport { issues })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GVWriter;
+    use std::io::Cursor;
+
+    const TEST_ALPHA_GV: &[u8; 4857] = include_bytes!("../test_asset/test-alpha.gv");
+    // 10px 5sec 1fps
+    const TEST_10PX_GV: &[u8; 474] = include_bytes!("../test_asset/test-10px.gv");
+
+    #[test]
+    fn valid_files() {
+        for data in [&TEST_10PX_GV[..], &TEST_ALPHA_GV[..]] {
+            let mut video = GVVideo::load(Cursor::new(data)).unwrap();
+            let report = video.validate().unwrap();
+            assert!(report.is_ok(), "{:?}", report);
+        }
+    }
+
+    #[test]
+    fn broken_table() {
+        let mut video = GVVideo::load(Cursor::new(&TEST_10PX_GV[..])).unwrap();
+        let blocks = video.address_size_blocks.clone();
+        video.address_size_blocks[1].address = 8;
+        video.address_size_blocks[2].address = blocks[0].address;
+        video.address_size_blocks[3].size = 0;
+        video.address_size_blocks[4].size = 1000;
+        video.header.frame_bytes = 100;
+
+        let report = video.validate().unwrap();
+        assert_eq!(
+            report.issues,
+            vec![
+                GVIssue::FrameBytesMismatch { stored: 100, expected: 72 },
+                GVIssue::OverlapsHeader { id: 1, address: 8 },
+                GVIssue::ZeroSizeFrame { id: 3 },
+                GVIssue::OutOfRange { id: 4, address: blocks[4].address, size: 1000 },
+                GVIssue::Overlap { id: 0, other: 2 },
+            ]
+        );
+    }
+
+    #[test]
+    fn overlaps_are_linear() {
+        // every frame points at the same block (table of 20 frames still leaves room for it in the file)
+        let mut video = GVVideo::load(Cursor::new(&TEST_10PX_GV[..])).unwrap();
+        let first = video.address_size_blocks[0];
+        video.address_size_blocks = vec![first; 20];
+        video.header.frame_count = 20;
+        let report = video.validate().unwrap();
+        let overlaps: Vec<&GVIssue> = report.issues.iter().filter(|issue| matches!(issue, GVIssue::Overlap { .. })).collect();
+        assert_eq!(overlaps.len(), 19);
+        assert_eq!(overlaps[18], &GVIssue::Overlap { id: 0, other: 19 });
+    }
+
+    #[test]
+    fn file_too_small() {
+        // header and address table without frame data
+        let mut data = TEST_10PX_GV[..HEADER_SIZE].to_vec();
+        data.extend_from_slice(&TEST_10PX_GV[TEST_10PX_GV.len() - 5 * 16..]);
+        let mut video = GVVideo::load(Cursor::new(data)).unwrap();
+        let report = video.validate().unwrap();
+        assert_eq!(report.issues[0], GVIssue::FileTooSmall { file_size: 24 + 5 * 16, min_size: 24 + 5 * 17 });
+        assert!(report.issues[1..].iter().all(|issue| matches!(issue, GVIssue::OutOfRange { .. })));
+    }
+
+    #[test]
+    fn lz4_length_mismatch() {
+        // frames of a 4x4 video written as 8x8 DXT1
+        let mut writer = GVWriter::new(Cursor::new(Vec::new()), 4, 4, 30.0, crate::GVFormat::DXT1).unwrap();
+        writer.write_frame(&[0; 8]).unwrap();
+        writer.write_frame(&[0; 8]).unwrap();
+        let mut data = writer.finish().unwrap().into_inner();
+        data[0] = 8;
+        data[4] = 8;
+        data[20] = 32;
+        // corrupt lz4 token of the second frame
+        let video = GVVideo::load(Cursor::new(data.clone())).unwrap();
+        let second = video.address_size_blocks[1];
+        data[second.address as usize] = 0xff;
+
+        let mut video = GVVideo::load(Cursor::new(data)).unwrap();
+        let report = video.validate().unwrap();
+        assert_eq!(report.issues[0], GVIssue::Lz4LengthMismatch { id: 0, expected: 32, actual: 8 });
+        assert!(matches!(report.issues[1], GVIssue::Lz4Error { id: 1, .. }));
+        assert_eq!(report.issues.len(), 2);
+    }
+}
@@ -0,0 +1,194 @@
+use std::io::{Seek, SeekFrom, Write};
+
+use byteorder::{LittleEndian, WriteBytesExt};
+
+use crate::{GVAddressSizeBlock, GVError, GVFormat, GVHeader, HEADER_SIZE};
+
+pub fn write_header<Writer>(writer: &mut Writer, header: &GVHeader) -> Result<(), GVError> where Writer: std::io::Write {
+    writer.write_u32::<LittleEndian>(header.width)?;
+    writer.write_u32::<LittleEndian>(header.height)?;
+    writer.write_u32::<LittleEndian>(header.frame_count)?;
+    writer.write_f32::<LittleEndian>(header.fps)?;
+    writer.write_u32::<LittleEndian>(header.format as u32)?;
+    writer.write_u32::<LittleEndian>(header.frame_bytes)?;
+    Ok(())
+}
+
+/// GV video encoder (LZ4 compression only)
+///
+/// Frames are passed as BC compressed data, the same layout as `GVVideo::read_frame_compressed` returns.
+/// The writer must be positioned at the file head, because addresses are zero based from it.
+#[derive(Debug)]
+pub struct GVWriter<Writer: Write + Seek> {
+    header: GVHeader,
+    address_size_blocks: Vec<GVAddressSizeBlock>,
+    writer: Writer,
+    position: u64,
+}
+
+impl<Writer: Write + Seek> GVWriter<Writer> {
+    /// write header (frame count is fixed up on `finish()`)
+    pub fn new(mut writer: Writer, width: u32, height: u32, fps: f32, format: GVFormat) -> Result<GVWriter<Writer>, GVError> {
+        let header = GVHeader {
+            width,
+            height,
+            frame_count: 0,
+            fps,
+            format,
+            frame_bytes: 0,
+        };
+        let frame_bytes = format.frame_bytes(width, height);
+        let frame_bytes = u32::try_from(frame_bytes)
+            .map_err(|_| GVError::LimitExceeded { what: "frame bytes", value: frame_bytes as u64, limit: u32::MAX as u64 })?;
+        let header = GVHeader { frame_bytes, ..header };
+        write_header(&mut writer, &header)?;
+        Ok(GVWriter {
+            header,
+            address_size_blocks: Vec::new(),
+            writer,
+            position: HEADER_SIZE as u64,
+        })
+    }
+
+    /// compress BC compressed frame data (BC1, BC2, BC3, BC7) with lz4, then append it
+    pub fn write_frame(&mut self, data: &[u8]) -> Result<(), GVError> {
+        let expected = self.header.frame_bytes as usize;
+        if data.len() != expected {
+            return Err(GVError::InvalidFrameSize { expected, actual: data.len() });
+        }
+
+        self.write_frame_lz4(&lz4_flex::block::compress(data))
+    }
+
+    /// append a frame which is already lz4 compressed, it must decompress to exactly frame bytes
+    pub fn write_frame_lz4(&mut self, lz4: &[u8]) -> Result<(), GVError> {
+        let frame_count = self.header.frame_count.checked_add(1).ok_or(GVError::LimitExceeded {
+            what: "frame count",
+            value: self.header.frame_count as u64 + 1,
+            limit: u32::MAX as u64,
+        })?;
+        self.writer.write_all(lz4)?;
+
+        self.address_size_blocks.push(GVAddressSizeBlock {
+            address: self.position,
+            size: lz4.len() as u64,
+        });
+        self.position += lz4.len() as u64;
+        self.header.frame_count = frame_count;
+        Ok(())
+    }
+
+    /// write address table and fix up header, then return inner writer
+    pub fn finish(mut self) -> Result<Writer, GVError> {
+        for block in &self.address_size_blocks {
+            self.writer.write_u64::<LittleEndian>(block.address)?;
+            self.writer.write_u64::<LittleEndian>(block.size)?;
+        }
+
+        self.writer.seek(SeekFrom::Start(0))?;
+        write_header(&mut self.writer, &self.header)?;
+        self.writer.seek(SeekFrom::End(0))?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    pub fn get_header(&self) -> &GVHeader {
+        &self.header
+    }
+
+    pub fn get_frame_count(&self) -> u32 {
+        self.header.frame_count
+    }
+
+    pub fn get_address_size_blocks(&self) -> &[GVAddressSizeBlock] {
+        &self.address_size_blocks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GVVideo;
+    use std::io::Cursor;
+
+    const TEST_GV: &[u8; 1547] = include_bytes!("../test_asset/test.gv");
+    const TEST_ALPHA_GV: &[u8; 4857] = include_bytes!("../test_asset/test-alpha.gv");
+    const TEST_10PX_GV: &[u8; 474] = include_bytes!("../test_asset/test-10px.gv");
+
+    fn rewrite(data: &[u8]) -> Vec<u8> {
+        let mut video = GVVideo::load(Cursor::new(data)).unwrap();
+        let header = &video.header;
+        let mut writer = GVWriter::new(Cursor::new(Vec::new()), header.width, header.height, header.fps, header.format).unwrap();
+        for i in 0..video.get_frame_count() {
+            let frame = video.read_frame_compressed(i).unwrap();
+            writer.write_frame(&frame).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn round_trip() {
+        for data in [&TEST_GV[..], &TEST_ALPHA_GV[..], &TEST_10PX_GV[..]] {
+            let written = rewrite(data);
+            // header is byte compatible with ofxExtremeGpuVideo
+            assert_eq!(written[..HEADER_SIZE], data[..HEADER_SIZE]);
+
+            let mut original = GVVideo::load(Cursor::new(data)).unwrap();
+            let mut video = GVVideo::load(Cursor::new(written)).unwrap();
+            assert_eq!(video.header, original.header);
+            for i in 0..original.get_frame_count() {
+                assert_eq!(video.read_frame_compressed(i).unwrap(), original.read_frame_compressed(i).unwrap());
+                assert_eq!(video.read_frame(i).unwrap(), original.read_frame(i).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn byte_compatible() {
+        // lz4_flex compresses differently from the lz4 library used by GVEncoder, so blocks are copied as they are
+        for data in [&TEST_GV[..], &TEST_ALPHA_GV[..], &TEST_10PX_GV[..]] {
+            let video = GVVideo::load(Cursor::new(data)).unwrap();
+            let header = &video.header;
+            let mut writer = GVWriter::new(Cursor::new(Vec::new()), header.width, header.height, header.fps, header.format).unwrap();
+            for block in &video.address_size_blocks {
+                writer.write_frame_lz4(&data[block.address as usize..(block.address + block.size) as usize]).unwrap();
+            }
+            assert_eq!(writer.finish().unwrap().into_inner(), data);
+        }
+    }
+
+    #[test]
+    fn address_table() {
+        let written = rewrite(TEST_10PX_GV);
+        let video = GVVideo::load(Cursor::new(&written)).unwrap();
+        let blocks = &video.address_size_blocks;
+        assert_eq!(blocks.len(), 5);
+        assert_eq!(blocks[0].address, HEADER_SIZE as u64);
+        for pair in blocks.windows(2) {
+            assert_eq!(pair[0].address + pair[0].size, pair[1].address);
+        }
+        let last = blocks[4];
+        assert_eq!(last.address + last.size + 5 * 16, written.len() as u64);
+    }
+
+    #[test]
+    fn empty_video() {
+        let writer = GVWriter::new(Cursor::new(Vec::new()), 8, 8, 30.0, GVFormat::BC7).unwrap();
+        let written = writer.finish().unwrap().into_inner();
+        assert_eq!(written.len(), HEADER_SIZE);
+        let video = GVVideo::load(Cursor::new(written)).unwrap();
+        assert_eq!(video.get_frame_count(), 0);
+        assert_eq!(video.get_frame_bytes(), 64);
+    }
+
+    #[test]
+    fn invalid_frame_size() {
+        let mut writer = GVWriter::new(Cursor::new(Vec::new()), 10, 10, 1.0, GVFormat::DXT1).unwrap();
+        let res = writer.write_frame(&[0; 64]);
+        assert!(matches!(res, Err(GVError::InvalidFrameSize { expected: 72, actual: 64 })));
+        assert_eq!(writer.get_frame_count(), 0);
+
+        let res = GVWriter::new(Cursor::new(Vec::new()), 1 << 16, 1 << 16, 1.0, GVFormat::BC7);
+        assert!(matches!(res, Err(GVError::LimitExceeded { what: "frame bytes", .. })));
+    }
+}
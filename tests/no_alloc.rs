@@ -0,0 +1,57 @@
+// steady-state `read_frame_into` / `read_frame_compressed_into` must not allocate
+//
+// own test binary, because the counting allocator replaces the global allocator of the whole binary
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    io::Cursor,
+};
+
+use gv_video::GVVideo;
+
+// 10px 5sec 1fps
+const TEST_10PX_GV: &[u8; 474] = include_bytes!("../test_asset/test-10px.gv");
+
+/// counts allocations of the current thread, so tests running in parallel do not interfere
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[test]
+fn read_into_does_not_allocate() {
+    let mut video = GVVideo::load(Cursor::new(TEST_10PX_GV)).unwrap();
+    let mut buffer = vec![0; 10 * 10];
+    let mut compressed = vec![0; 10 * 10 * 4];
+    // warm up internal buffers
+    video.read_frame_into(0, &mut buffer).unwrap();
+    video.read_frame_compressed_into(0, &mut compressed).unwrap();
+
+    let before = ALLOCATIONS.with(|count| count.get());
+    for frame_id in 0..video.get_frame_count() {
+        video.read_frame_into(frame_id, &mut buffer).unwrap();
+        video.read_frame_compressed_into(frame_id, &mut compressed).unwrap();
+    }
+    assert_eq!(ALLOCATIONS.with(|count| count.get()), before);
+}